@@ -0,0 +1,73 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rand::{seq::SliceRandom, Rng};
+use typestrainer::{
+    chart::{compute_scores, Generation, Ruleset},
+    round::{deal, DUAL_TYPE_ODDS},
+    strategy::StrategyKind,
+    types::Choice,
+};
+
+fn random_rounds(ruleset: &Ruleset, count: usize) -> Vec<(Choice, Choice)> {
+    let mut rng = rand::thread_rng();
+    let choice = |rng: &mut rand::rngs::ThreadRng| -> Choice {
+        let size = if rng.gen_bool(0.5) { 2 } else { 1 };
+        ruleset.types.choose_multiple(rng, size).cloned().collect()
+    };
+    (0..count)
+        .map(|_| (choice(&mut rng), choice(&mut rng)))
+        .collect()
+}
+
+fn outcomes(c: &mut Criterion) {
+    let ruleset = Ruleset::from_generation(Generation::Gen6);
+    let rounds = random_rounds(&ruleset, 1024);
+
+    c.bench_function("round outcome", |b| {
+        let mut i = 0;
+        b.iter(|| {
+            let (p1, p2) = &rounds[i % rounds.len()];
+            i += 1;
+            compute_scores(black_box(p1), black_box(p2), &ruleset.chart)
+        })
+    });
+
+    c.bench_function("1024 round outcomes", |b| {
+        b.iter(|| {
+            rounds
+                .iter()
+                .map(|(p1, p2)| compute_scores(p1, p2, &ruleset.chart))
+                .fold(0.0, |total, (s1, s2)| total + s1 - s2)
+        })
+    });
+
+    c.bench_function("chart compilation", |b| {
+        b.iter(|| Ruleset::from_generation(black_box(Generation::Gen6)))
+    });
+
+    let mut rng = rand::thread_rng();
+    let hands: Vec<(Vec<Choice>, Vec<Choice>)> = (0..64)
+        .map(|_| {
+            (
+                deal(&mut rng, &ruleset.types, DUAL_TYPE_ODDS),
+                deal(&mut rng, &ruleset.types, DUAL_TYPE_ODDS),
+            )
+        })
+        .collect();
+    for (name, kind) in [
+        ("greedy", StrategyKind::Greedy),
+        ("nash", StrategyKind::Nash),
+    ] {
+        let mut strategy = kind.build();
+        c.bench_function(&format!("64 {} picks", name), |b| {
+            b.iter(|| {
+                hands
+                    .iter()
+                    .map(|(mine, theirs)| strategy.pick(&ruleset.chart, mine, theirs))
+                    .count()
+            })
+        });
+    }
+}
+
+criterion_group!(benches, outcomes);
+criterion_main!(benches);
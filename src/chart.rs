@@ -0,0 +1,473 @@
+use crate::types::{format_choice, Choice, PokemonType};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Generation {
+    Gen1,
+    Gen2To5,
+    Gen6,
+}
+
+impl Generation {
+    pub fn from_number(number: u8) -> Option<Generation> {
+        match number {
+            1 => Some(Generation::Gen1),
+            2..=5 => Some(Generation::Gen2To5),
+            6..=9 => Some(Generation::Gen6),
+            _ => None,
+        }
+    }
+
+    pub fn types(&self) -> Vec<PokemonType> {
+        let missing: &[PokemonType] = match self {
+            Generation::Gen1 => &[PokemonType::Dark, PokemonType::Steel, PokemonType::Fairy],
+            Generation::Gen2To5 => &[PokemonType::Fairy],
+            Generation::Gen6 => &[],
+        };
+        PokemonType::iter()
+            .filter(|t| !missing.contains(t))
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chart {
+    multipliers: [[f32; PokemonType::COUNT]; PokemonType::COUNT],
+}
+
+impl Chart {
+    pub fn new() -> Chart {
+        Chart {
+            multipliers: [[1.0; PokemonType::COUNT]; PokemonType::COUNT],
+        }
+    }
+
+    pub fn set(&mut self, attacker: PokemonType, defender: PokemonType, multiplier: f32) {
+        self.multipliers[attacker as usize][defender as usize] = multiplier;
+    }
+
+    // Inverse Battles swap super effective and not very effective, and
+    // immunities become super effective.
+    pub fn inverted(&self) -> Chart {
+        let mut chart = self.clone();
+        for row in chart.multipliers.iter_mut() {
+            for multiplier in row.iter_mut() {
+                *multiplier = if *multiplier == 0.0 {
+                    2.0
+                } else {
+                    1.0 / *multiplier
+                };
+            }
+        }
+        chart
+    }
+}
+
+impl Default for Chart {
+    fn default() -> Chart {
+        Chart::new()
+    }
+}
+
+pub struct Ruleset {
+    pub types: Vec<PokemonType>,
+    pub chart: Chart,
+    // Whether the chart was inverted for an Inverse Battle.
+    pub inverse: bool,
+}
+
+impl Ruleset {
+    pub fn from_generation(generation: Generation) -> Ruleset {
+        Ruleset {
+            types: generation.types(),
+            chart: make_chart(generation),
+            inverse: false,
+        }
+    }
+
+    pub fn inverted(&self) -> Ruleset {
+        Ruleset {
+            types: self.types.clone(),
+            chart: self.chart.inverted(),
+            inverse: true,
+        }
+    }
+}
+
+pub fn effectiveness(chart: &Chart, attacker: PokemonType, defender: PokemonType) -> f32 {
+    chart.multipliers[attacker as usize][defender as usize]
+}
+
+// A dual-type attacker uses whichever of its types hits hardest, and the
+// multipliers against each of the defender's types stack.
+pub fn choice_effectiveness(
+    chart: &Chart,
+    attacker: &[PokemonType],
+    defender: &[PokemonType],
+) -> f32 {
+    attacker
+        .iter()
+        .map(|a| {
+            defender
+                .iter()
+                .map(|d| effectiveness(chart, *a, *d))
+                .product::<f32>()
+        })
+        .fold(0.0, f32::max)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Matchup {
+    pub attacker: Choice,
+    pub defender: Choice,
+    pub used: PokemonType,
+    pub factors: Vec<f32>,
+}
+
+impl Matchup {
+    pub fn new(chart: &Chart, attacker: &[PokemonType], defender: &[PokemonType]) -> Matchup {
+        let mut best: Option<Matchup> = None;
+        for used in attacker.iter() {
+            let matchup = Matchup {
+                attacker: attacker.to_vec(),
+                defender: defender.to_vec(),
+                used: *used,
+                factors: defender
+                    .iter()
+                    .map(|d| effectiveness(chart, *used, *d))
+                    .collect(),
+            };
+            best = match best {
+                Some(b) if b.multiplier() >= matchup.multiplier() => Some(b),
+                _ => Some(matchup),
+            };
+        }
+        best.expect("Cannot explain an empty choice !")
+    }
+
+    pub fn multiplier(&self) -> f32 {
+        self.factors.iter().product()
+    }
+
+    pub fn label(&self) -> &'static str {
+        let multiplier = self.multiplier();
+        if multiplier == 0.0 {
+            "no effect"
+        } else if multiplier < 1.0 {
+            "not very effective"
+        } else if multiplier > 1.0 {
+            "super effective"
+        } else {
+            "neutral"
+        }
+    }
+
+    pub fn explain(&self) -> String {
+        let factors: Vec<String> = self
+            .defender
+            .iter()
+            .zip(self.factors.iter())
+            .map(|(d, f)| format!("{}x {}", f, d))
+            .collect();
+        let mut text = format!(
+            "{} vs {}: {}",
+            self.used,
+            format_choice(&self.defender),
+            factors.join(", ")
+        );
+        if self.factors.len() > 1 {
+            text += &format!(" = {}x", self.multiplier());
+        }
+        format!("{} ({})", text, self.label())
+    }
+}
+
+pub fn compute_scores(
+    p1_selected: &[PokemonType],
+    p2_selected: &[PokemonType],
+    chart: &Chart,
+) -> (f32, f32) {
+    (
+        choice_effectiveness(chart, p1_selected, p2_selected),
+        choice_effectiveness(chart, p2_selected, p1_selected),
+    )
+}
+
+pub fn insert_row(
+    chart: &mut Chart,
+    attacker: PokemonType,
+    super_effective: &[PokemonType],
+    not_very_effective: &[PokemonType],
+    no_effect: &[PokemonType],
+) {
+    for defender in super_effective {
+        chart.set(attacker, *defender, 2.0);
+    }
+    for defender in not_very_effective {
+        chart.set(attacker, *defender, 0.5);
+    }
+    for defender in no_effect {
+        chart.set(attacker, *defender, 0.0);
+    }
+}
+
+pub fn make_chart(generation: Generation) -> Chart {
+    use PokemonType::*;
+
+    let mut c = make_gen6_chart();
+
+    if generation == Generation::Gen2To5 {
+        c.set(Ghost, Steel, 0.5);
+        c.set(Dark, Steel, 0.5);
+    }
+
+    if generation == Generation::Gen1 {
+        c.set(Ghost, Psychic, 0.0);
+        c.set(Bug, Poison, 2.0);
+        c.set(Poison, Bug, 2.0);
+        c.set(Ice, Fire, 1.0);
+    }
+    c
+}
+
+fn make_gen6_chart() -> Chart {
+    use PokemonType::*;
+
+    let mut c = Chart::new();
+    insert_row(
+        &mut c,
+        Bug,
+        &[Dark, Grass, Psychic],
+        &[Fire, Fighting, Flying, Poison, Ghost, Steel, Fairy],
+        &[],
+    );
+    insert_row(
+        &mut c,
+        Dark,
+        &[Psychic, Ghost],
+        &[Fighting, Dark, Fairy],
+        &[],
+    );
+    insert_row(&mut c, Dragon, &[Dragon], &[Steel], &[Fairy]);
+    insert_row(
+        &mut c,
+        Electric,
+        &[Water, Flying],
+        &[Electric, Grass, Dragon],
+        &[Ground],
+    );
+    insert_row(
+        &mut c,
+        Fairy,
+        &[Dragon, Fighting, Dark],
+        &[Fire, Poison, Steel],
+        &[],
+    );
+    insert_row(
+        &mut c,
+        Fighting,
+        &[Rock, Normal, Dark, Steel, Ice],
+        &[Flying, Poison, Psychic, Bug, Fairy],
+        &[Ghost],
+    );
+    insert_row(
+        &mut c,
+        Fire,
+        &[Ice, Grass, Bug, Steel],
+        &[Fire, Water, Rock, Dragon],
+        &[],
+    );
+    insert_row(
+        &mut c,
+        Flying,
+        &[Grass, Fighting, Bug],
+        &[Electric, Rock, Steel],
+        &[],
+    );
+    insert_row(&mut c, Ghost, &[Ghost, Psychic], &[Dark], &[Normal]);
+    insert_row(
+        &mut c,
+        Grass,
+        &[Rock, Ground, Water],
+        &[Fire, Grass, Poison, Flying, Bug, Dragon, Steel],
+        &[],
+    );
+    insert_row(
+        &mut c,
+        Ground,
+        &[Steel, Rock, Fire, Poison, Electric],
+        &[Grass, Bug],
+        &[Flying],
+    );
+    insert_row(
+        &mut c,
+        Ice,
+        &[Flying, Grass, Ground, Dragon],
+        &[Ice, Fire, Water, Steel],
+        &[],
+    );
+    insert_row(
+        &mut c,
+        Poison,
+        &[Grass, Fairy],
+        &[Poison, Ground, Rock, Ghost],
+        &[Steel],
+    );
+    insert_row(
+        &mut c,
+        Psychic,
+        &[Fighting, Poison],
+        &[Psychic, Steel],
+        &[Dark],
+    );
+    insert_row(
+        &mut c,
+        Rock,
+        &[Bug, Flying, Fire, Ice],
+        &[Fighting, Ground, Steel],
+        &[],
+    );
+    insert_row(
+        &mut c,
+        Steel,
+        &[Fairy, Ice, Rock],
+        &[Fire, Water, Electric, Steel],
+        &[],
+    );
+    insert_row(
+        &mut c,
+        Water,
+        &[Ground, Fire, Rock],
+        &[Water, Grass, Dragon],
+        &[],
+    );
+    insert_row(&mut c, Normal, &[], &[Rock, Steel], &[Ghost]);
+    c
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::PokemonType::*;
+
+    #[test]
+    fn test_compute_scores() {
+        let mut c = Chart::new();
+        insert_row(&mut c, Bug, &[Grass], &[], &[]);
+        insert_row(&mut c, Grass, &[Rock], &[Bug], &[]);
+        insert_row(&mut c, Normal, &[], &[Rock], &[Ghost]);
+        insert_row(&mut c, Rock, &[Bug], &[Grass], &[]);
+        insert_row(&mut c, Ghost, &[], &[], &[Normal]);
+
+        assert_eq!(compute_scores(&[Bug], &[Grass], &c), (2.0, 0.5));
+
+        assert_eq!(compute_scores(&[Normal], &[Rock], &c), (0.5, 1.0));
+
+        assert_eq!(compute_scores(&[Normal], &[Normal], &c), (1.0, 1.0));
+
+        assert_eq!(compute_scores(&[Normal], &[Ghost], &c), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_compute_scores_dual_types() {
+        let c = make_chart(Generation::Gen6);
+
+        assert_eq!(compute_scores(&[Ice], &[Dragon, Ground], &c), (4.0, 1.0));
+
+        assert_eq!(compute_scores(&[Fire], &[Water, Dragon], &c), (0.25, 2.0));
+
+        assert_eq!(
+            compute_scores(&[Electric], &[Water, Ground], &c),
+            (0.0, 2.0)
+        );
+
+        assert_eq!(
+            compute_scores(&[Fire, Ground], &[Steel, Fairy], &c),
+            (2.0, 0.5)
+        );
+    }
+
+    #[test]
+    fn test_matchup() {
+        let c = make_chart(Generation::Gen6);
+
+        let matchup = Matchup::new(&c, &[Fire, Ground], &[Steel, Fairy]);
+        assert_eq!(matchup.used, Fire);
+        assert_eq!(matchup.factors, vec![2.0, 1.0]);
+        assert_eq!(matchup.multiplier(), 2.0);
+        assert_eq!(
+            matchup.explain(),
+            "fire vs steel/fairy: 2x steel, 1x fairy = 2x (super effective)"
+        );
+
+        let matchup = Matchup::new(&c, &[Electric], &[Water, Ground]);
+        assert_eq!(matchup.multiplier(), 0.0);
+        assert_eq!(
+            matchup.explain(),
+            "electric vs water/ground: 2x water, 0x ground = 0x (no effect)"
+        );
+
+        let matchup = Matchup::new(&c, &[Fire], &[Water]);
+        assert_eq!(
+            matchup.explain(),
+            "fire vs water: 0.5x water (not very effective)"
+        );
+    }
+
+    #[test]
+    fn test_inverted_chart() {
+        let c = make_chart(Generation::Gen6).inverted();
+        let ruleset = Ruleset::from_generation(Generation::Gen6);
+        assert!(!ruleset.inverse && ruleset.inverted().inverse);
+
+        assert_eq!(effectiveness(&c, Fire, Grass), 0.5);
+        assert_eq!(effectiveness(&c, Fire, Water), 2.0);
+        assert_eq!(effectiveness(&c, Normal, Ghost), 2.0);
+        assert_eq!(effectiveness(&c, Water, Normal), 1.0);
+        assert_eq!(compute_scores(&[Ice], &[Dragon, Ground], &c), (0.25, 1.0));
+        assert_eq!(
+            compute_scores(&[Electric], &[Water, Ground], &c),
+            (1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn test_chart_immunities() {
+        let c = make_chart(Generation::Gen6);
+
+        assert_eq!(effectiveness(&c, Ground, Flying), 0.0);
+        assert_eq!(effectiveness(&c, Electric, Ground), 0.0);
+        assert_eq!(effectiveness(&c, Normal, Ghost), 0.0);
+        assert_eq!(effectiveness(&c, Fighting, Ghost), 0.0);
+        assert_eq!(effectiveness(&c, Ghost, Normal), 0.0);
+        assert_eq!(effectiveness(&c, Dragon, Fairy), 0.0);
+        assert_eq!(effectiveness(&c, Psychic, Dark), 0.0);
+        assert_eq!(effectiveness(&c, Poison, Steel), 0.0);
+        assert_eq!(effectiveness(&c, Ground, Electric), 2.0);
+        assert_eq!(effectiveness(&c, Grass, Dragon), 0.5);
+        assert_eq!(effectiveness(&c, Water, Normal), 1.0);
+    }
+
+    #[test]
+    fn test_generation_charts() {
+        assert_eq!(Generation::Gen1.types().len(), 15);
+        assert_eq!(Generation::Gen2To5.types().len(), 17);
+        assert_eq!(Generation::Gen6.types().len(), 18);
+
+        let gen1 = make_chart(Generation::Gen1);
+        assert_eq!(effectiveness(&gen1, Ghost, Psychic), 0.0);
+        assert_eq!(effectiveness(&gen1, Bug, Poison), 2.0);
+        assert_eq!(effectiveness(&gen1, Poison, Bug), 2.0);
+        assert_eq!(effectiveness(&gen1, Ice, Fire), 1.0);
+
+        let gen2 = make_chart(Generation::Gen2To5);
+        assert_eq!(effectiveness(&gen2, Ghost, Psychic), 2.0);
+        assert_eq!(effectiveness(&gen2, Ghost, Steel), 0.5);
+        assert_eq!(effectiveness(&gen2, Dark, Steel), 0.5);
+        assert!(!Generation::Gen2To5.types().contains(&Fairy));
+
+        let gen6 = make_chart(Generation::Gen6);
+        assert_eq!(effectiveness(&gen6, Ghost, Steel), 1.0);
+        assert_eq!(effectiveness(&gen6, Dark, Steel), 1.0);
+    }
+}
@@ -1,45 +1,37 @@
 mod libclient;
 
 use crate::libclient::{
-    drawing::{draw_choices, draw_outcome, retry},
-    state::{GameState, Outcome},
+    config::Config,
+    connection::Connection,
+    drawing::{
+        draw_choices, draw_code_entry, draw_countdown, draw_lobby, draw_outcome, draw_queued,
+        draw_quiz, draw_timeout, draw_watching, retry, LOBBY_ENTRIES, QUIZ_COLUMNS,
+    },
+    practice::Practice,
+    quiz::{Grade, Quiz},
+    state::{GameState, Spectated},
     textures::TextureStore,
-    utils::parse_choices,
 };
-use futures_channel::{
-    mpsc,
-    mpsc::{UnboundedReceiver, UnboundedSender},
-};
-use futures_util::{future, pin_mut, StreamExt};
-use raylib::prelude::*;
+use raylib::{core::input::key_from_i32, prelude::*};
 use std::{
     cmp::{max, min},
     env,
+    time::{Duration, Instant},
+};
+use typestrainer::{
+    protocol::{ClientMessage, ServerMessage},
+    score::MatchScore,
+    strategy::StrategyKind,
+    types::{Choice, PokemonType},
 };
-use tokio::spawn;
-use tokio_tungstenite::tungstenite::Error as TungsteniteError;
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
-
-type ReadRx = UnboundedReceiver<Result<Message, TungsteniteError>>;
-type WriteTx = UnboundedSender<Message>;
-
-fn get_message(read_rx: &mut ReadRx) -> Option<String> {
-    let message: String;
-    match read_rx.try_next() {
-        Ok(Some(msg)) => {
-            println!("{:?}", msg);
-            message = msg.unwrap().to_string();
-            Some(message)
-        }
-        Ok(None) | Err(_) => None,
-    }
-}
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
 
 fn handle_input(
     draw_handle: &mut RaylibDrawHandle,
-    mine: &[String],
+    mine: &[Choice],
     hoover_index: usize,
-) -> (Option<String>, Option<usize>) {
+) -> (Option<Choice>, Option<usize>) {
     if draw_handle.is_key_pressed(KeyboardKey::KEY_LEFT) {
         (None, Some(max(1, hoover_index) - 1))
     } else if draw_handle.is_key_pressed(KeyboardKey::KEY_RIGHT) {
@@ -51,63 +43,509 @@ fn handle_input(
     }
 }
 
-fn parse_outcome(message: String) -> (Outcome, String, String) {
-    let parsed: Vec<String> = message.splitn(3, ';').map(String::from).collect();
+fn handle_lobby_input(
+    draw_handle: &mut RaylibDrawHandle,
+    entries: usize,
+    hoover_index: usize,
+) -> (Option<usize>, Option<usize>) {
+    if draw_handle.is_key_pressed(KeyboardKey::KEY_UP) {
+        (None, Some(max(1, hoover_index) - 1))
+    } else if draw_handle.is_key_pressed(KeyboardKey::KEY_DOWN) {
+        (None, Some(min(entries - 1, hoover_index + 1)))
+    } else if draw_handle.is_key_pressed(KeyboardKey::KEY_ENTER) {
+        (Some(hoover_index), None)
+    } else {
+        (None, Some(hoover_index))
+    }
+}
 
-    let (status, yours, theirs) = (parsed[0].clone(), parsed[1].clone(), parsed[2].clone());
+fn handle_code_input(draw_handle: &mut RaylibDrawHandle, code: &str) -> (Option<String>, String) {
+    let mut code = code.to_string();
+    if draw_handle.is_key_pressed(KeyboardKey::KEY_ENTER) {
+        return (Some(code.clone()), code);
+    }
+    if draw_handle.is_key_pressed(KeyboardKey::KEY_BACKSPACE) {
+        code.pop();
+    }
+    // Key codes of letters and digits are their uppercase ascii values.
+    for c in ('A'..='Z').chain('0'..='9') {
+        match key_from_i32(c as i32) {
+            Some(key) if draw_handle.is_key_pressed(key) => code.push(c),
+            _ => (),
+        }
+    }
+    (None, code)
+}
 
-    let outcome = match &*status {
-        "won" => Outcome::Won,
-        "lost" => Outcome::Lost,
-        "tie" => Outcome::Tie,
-        _ => panic!("wtf?"),
-    };
-    (outcome, yours, theirs)
+fn handle_grid_input(
+    draw_handle: &mut RaylibDrawHandle,
+    cells: usize,
+    hoover_index: usize,
+) -> usize {
+    if draw_handle.is_key_pressed(KeyboardKey::KEY_LEFT) {
+        max(1, hoover_index) - 1
+    } else if draw_handle.is_key_pressed(KeyboardKey::KEY_RIGHT) {
+        min(cells - 1, hoover_index + 1)
+    } else if draw_handle.is_key_pressed(KeyboardKey::KEY_UP) && hoover_index >= QUIZ_COLUMNS {
+        hoover_index - QUIZ_COLUMNS
+    } else if draw_handle.is_key_pressed(KeyboardKey::KEY_DOWN) {
+        min(cells - 1, hoover_index + QUIZ_COLUMNS)
+    } else {
+        hoover_index
+    }
 }
 
-async fn main_loop(mut read_rx: ReadRx, write_tx: WriteTx) {
-    let mut gamestate = GameState::WaitingForChoices;
-    let mut scores = (0, 0);
+fn join_room(id: u32, types: &[PokemonType], connection: &mut Connection) -> u32 {
+    println!("joined room {} playing with {} types", id, types.len());
+    connection.send(&ClientMessage::Ready);
+    id
+}
 
-    write_tx
-        .unbounded_send(Message::Text("ready:_".to_string()))
-        .unwrap();
-    println!("ready");
+async fn main_loop(config: Config, mut connection: Connection) {
+    // A practice match is joined right away.
+    let mut gamestate = if config.offline {
+        GameState::JoiningRoom
+    } else {
+        GameState::Lobby(vec![], 0)
+    };
+    let mut scores = (0, 0);
+    let mut room = None;
+    let mut private_code: Option<String> = None;
+    let mut notice: Option<String> = None;
+    let mut code_from_args = config.code.clone();
+    let mut session: Option<String> = None;
+    let mut opponent_away: Option<Instant> = None;
+    let mut latency: Option<u64> = None;
+    // Of the current selection, or of the start of the next round.
+    let mut deadline: Option<Instant> = None;
+    let mut last_attempt = Instant::now();
 
     set_trace_log(TraceLogType::LOG_FATAL);
     let (mut handle, thread) = raylib::init().size(640, 480).title("Hello, World").build();
     handle.set_target_fps(60);
-    let ts = TextureStore::new(&mut handle, &thread);
+    let ts = TextureStore::new(&mut handle, &thread, &PokemonType::ALL);
 
     while !handle.window_should_close() {
         let mut draw_handle = handle.begin_drawing(&thread);
         draw_handle.clear_background(Color::WHITE);
 
         draw_handle.draw_text(
-            &*format!("{}/{}", scores.0, scores.1),
+            &format!("{}/{}", scores.0, scores.1),
             615,
             10,
             10,
             Color::BLACK,
         );
 
+        if let Some(ms) = latency {
+            draw_handle.draw_text(&format!("{} ms", ms), 560, 10, 10, Color::GRAY);
+        }
+
+        if let Some(id) = room {
+            let text = match private_code {
+                _ if config.offline => "practice against the bot".to_string(),
+                Some(ref code) => format!("room {}, code {}", id, code),
+                None => format!("room {}", id),
+            };
+            draw_handle.draw_text(&text, 10, 465, 10, Color::GRAY);
+        }
+
+        if connection.is_closed() {
+            draw_handle.draw_text(
+                "Connection lost, reconnecting ...",
+                10,
+                10,
+                10,
+                Color::BLACK,
+            );
+            if last_attempt.elapsed() < RECONNECT_DELAY {
+                continue;
+            }
+            last_attempt = Instant::now();
+            match Connection::open(&config.url).await {
+                Ok(reopened) => {
+                    connection = reopened;
+                    latency = None;
+                }
+                Err(e) => {
+                    println!("cannot reconnect: {}", e);
+                    continue;
+                }
+            }
+            // Take our slot back when we were in a room, or start over.
+            gamestate = match session {
+                Some(ref session) => {
+                    connection.send(&ClientMessage::Resume {
+                        session: session.clone(),
+                    });
+                    GameState::Resuming
+                }
+                None => GameState::Lobby(vec![], 0),
+            };
+            continue;
+        }
+
+        let mut message = connection.receive();
+        match message {
+            Some(ServerMessage::Ping { id, rtt }) => {
+                connection.send(&ClientMessage::Pong { id });
+                latency = rtt.or(latency);
+                message = None;
+            }
+            Some(ServerMessage::OpponentDisconnected { grace }) => {
+                opponent_away = Some(Instant::now() + Duration::from_secs(grace));
+                message = None;
+            }
+            Some(ServerMessage::OpponentReconnected) => {
+                opponent_away = None;
+                message = None;
+            }
+            // The server sends us back to the lobby or the queue.
+            Some(ServerMessage::Forfeit { score }) => {
+                notice = Some(format!(
+                    "Your opponent left, you win the match {}-{} by forfeit !",
+                    score.wins.0, score.wins.1
+                ));
+                opponent_away = None;
+                session = None;
+                room = None;
+                scores = (0, 0);
+                gamestate = GameState::JoiningRoom;
+                message = None;
+            }
+            // Rounds go on with the server timers, whatever the state.
+            Some(ServerMessage::Choices {
+                yours,
+                theirs,
+                time,
+            }) if room.is_some() => {
+                deadline = Some(Instant::now() + Duration::from_secs(time));
+                gamestate = GameState::GotChoices(yours, theirs, 1);
+                message = None;
+            }
+            Some(ServerMessage::Outcome {
+                outcome,
+                yours,
+                theirs,
+                score,
+                next_round,
+            }) if room.is_some() => {
+                scores = score.wins;
+                deadline = Some(Instant::now() + Duration::from_secs(next_round));
+                gamestate = GameState::GotOutcome(outcome, yours, theirs, score);
+                message = None;
+            }
+            Some(ServerMessage::Timeout {
+                outcome,
+                score,
+                next_round,
+            }) if room.is_some() => {
+                scores = score.wins;
+                deadline = Some(Instant::now() + Duration::from_secs(next_round));
+                gamestate = GameState::GotTimeout(outcome, score);
+                message = None;
+            }
+            _ => (),
+        }
+        let countdown = deadline.map(|d| d.saturating_duration_since(Instant::now()).as_secs());
+        if let Some(until) = opponent_away {
+            let left = until.saturating_duration_since(Instant::now()).as_secs();
+            draw_handle.draw_text(
+                &format!("Opponent disconnected, forfeit in {}s", left),
+                10,
+                450,
+                10,
+                Color::RED,
+            );
+        }
+
         match gamestate {
+            GameState::Lobby(ref rooms, hoover_index) => {
+                draw_lobby(&mut draw_handle, rooms, hoover_index, notice.as_deref());
+                private_code = None;
+                match message {
+                    Some(ServerMessage::Rooms { rooms }) => {
+                        let hoover_index = min(rooms.len() + LOBBY_ENTRIES.len() - 1, hoover_index);
+                        gamestate = GameState::Lobby(rooms, hoover_index);
+                        // Join the room given on the command line once connected.
+                        if let Some(code) = code_from_args.take() {
+                            connection.send(&config.enter_message(&code));
+                            private_code = Some(code.to_uppercase());
+                            gamestate = GameState::JoiningRoom;
+                        }
+                        continue;
+                    }
+                    Some(ServerMessage::Welcome {
+                        server,
+                        server_version,
+                        features,
+                    }) => println!(
+                        "connected to {} {}, features {:?}",
+                        server, server_version, features
+                    ),
+                    Some(ServerMessage::Error(error)) => notice = Some(error.message),
+                    Some(message) => println!("unexpected message: {:?}", message),
+                    None => (),
+                }
+
+                if hoover_index >= LOBBY_ENTRIES.len()
+                    && draw_handle.is_key_pressed(KeyboardKey::KEY_W)
+                {
+                    let room = rooms[hoover_index - LOBBY_ENTRIES.len()].id;
+                    connection.send(&ClientMessage::Watch { room });
+                    gamestate = GameState::JoiningRoom;
+                    continue;
+                }
+
+                let entries = rooms.len() + LOBBY_ENTRIES.len();
+                match handle_lobby_input(&mut draw_handle, entries, hoover_index) {
+                    (Some(0), None) => {
+                        connection.send(&config.find_message());
+                        gamestate = GameState::Queued(0);
+                    }
+                    (Some(1), None) => {
+                        connection.send(&config.create_message());
+                        gamestate = GameState::JoiningRoom;
+                    }
+                    (Some(2), None) => {
+                        connection.send(&config.private_message());
+                        gamestate = GameState::JoiningRoom;
+                    }
+                    (Some(3), None) => {
+                        gamestate = GameState::EnteringCode(String::new());
+                    }
+                    (Some(4), None) => {
+                        connection.send(&config.bot_message());
+                        gamestate = GameState::JoiningRoom;
+                    }
+                    (Some(index), None) => {
+                        let room = rooms[index - LOBBY_ENTRIES.len()].id;
+                        connection.send(&ClientMessage::Join { room });
+                        gamestate = GameState::JoiningRoom;
+                    }
+                    (None, Some(hoover_index)) => {
+                        gamestate = GameState::Lobby(rooms.to_vec(), hoover_index);
+                    }
+                    _ => panic!("invalid state!"),
+                }
+            }
+            GameState::EnteringCode(ref code) => {
+                draw_code_entry(&mut draw_handle, code);
+                if code.is_empty() && draw_handle.is_key_pressed(KeyboardKey::KEY_BACKSPACE) {
+                    gamestate = GameState::Lobby(vec![], 0);
+                    connection.send(&ClientMessage::List);
+                    continue;
+                }
+                match handle_code_input(&mut draw_handle, code) {
+                    (Some(code), _) => {
+                        connection.send(&config.enter_message(&code));
+                        private_code = Some(code);
+                        gamestate = GameState::JoiningRoom;
+                    }
+                    (None, code) => gamestate = GameState::EnteringCode(code),
+                }
+            }
+            GameState::Queued(position) => {
+                draw_queued(&mut draw_handle, position);
+                match message {
+                    Some(ServerMessage::Queued { position }) => {
+                        gamestate = GameState::Queued(position);
+                    }
+                    Some(ServerMessage::Joined {
+                        room: id,
+                        types,
+                        session: joined,
+                    }) => {
+                        room = Some(join_room(id, &types, &mut connection));
+                        session = Some(joined);
+                        notice = None;
+                        gamestate = GameState::WaitingForChoices;
+                    }
+                    Some(ServerMessage::Rooms { .. }) => (),
+                    Some(message) => println!("unexpected message: {:?}", message),
+                    None if draw_handle.is_key_pressed(KeyboardKey::KEY_BACKSPACE) => {
+                        // A match may have been found meanwhile, the server then
+                        // answers with the room instead of the lobby.
+                        connection.send(&ClientMessage::Cancel);
+                        gamestate = GameState::JoiningRoom;
+                    }
+                    None => (),
+                }
+            }
+            GameState::JoiningRoom => {
+                draw_handle.draw_text("Joining room ...", 10, 10, 10, Color::BLACK);
+                match message {
+                    // The server answers with the room list again when the room
+                    // could not be joined.
+                    Some(ServerMessage::Rooms { rooms }) => {
+                        gamestate = GameState::Lobby(rooms, 0);
+                    }
+                    Some(ServerMessage::Queued { position }) => {
+                        gamestate = GameState::Queued(position);
+                    }
+                    Some(ServerMessage::Error(error)) => notice = Some(error.message),
+                    Some(ServerMessage::Watching {
+                        room: id,
+                        types: _,
+                        score,
+                        p1,
+                        p2,
+                        p1_pick,
+                        p2_pick,
+                    }) => {
+                        notice = None;
+                        gamestate = GameState::Watching(Spectated {
+                            room: id,
+                            score,
+                            p1,
+                            p2,
+                            p1_pick,
+                            p2_pick,
+                            last: None,
+                        });
+                    }
+                    Some(ServerMessage::Code { code }) => {
+                        println!("created private room, share the code {}", code);
+                        private_code = Some(code);
+                    }
+                    Some(ServerMessage::Joined {
+                        room: id,
+                        types,
+                        session: joined,
+                    }) => {
+                        room = Some(join_room(id, &types, &mut connection));
+                        session = Some(joined);
+                        notice = None;
+                        gamestate = GameState::WaitingForChoices;
+                    }
+                    Some(message) => println!("unexpected message: {:?}", message),
+                    None => (),
+                }
+            }
+            GameState::Resuming => {
+                draw_handle.draw_text("Resuming match ...", 10, 10, 10, Color::BLACK);
+                match message {
+                    Some(ServerMessage::Snapshot {
+                        room: id,
+                        code,
+                        types,
+                        score,
+                        ready,
+                        yours,
+                        theirs,
+                        selected,
+                        time,
+                    }) => {
+                        deadline = time.map(|time| Instant::now() + Duration::from_secs(time));
+                        println!("resumed room {} playing with {} types", id, types.len());
+                        room = Some(id);
+                        private_code = code;
+                        scores = score.wins;
+                        gamestate = match (yours, theirs, selected) {
+                            (Some(_), _, Some(_)) => GameState::WaitingForOtherSelected,
+                            (Some(yours), Some(theirs), None) => {
+                                GameState::GotChoices(yours, theirs, 1)
+                            }
+                            _ => {
+                                if !ready {
+                                    connection.send(&ClientMessage::Ready);
+                                }
+                                GameState::WaitingForChoices
+                            }
+                        };
+                    }
+                    // The session expired, back to the lobby.
+                    Some(ServerMessage::Error(error)) => {
+                        notice = Some(error.message);
+                        session = None;
+                        room = None;
+                        scores = (0, 0);
+                        connection.send(&ClientMessage::List);
+                        gamestate = GameState::Lobby(vec![], 0);
+                    }
+                    Some(ServerMessage::Welcome { .. }) | Some(ServerMessage::Rooms { .. }) => (),
+                    Some(message) => println!("unexpected message: {:?}", message),
+                    None => (),
+                }
+            }
+            GameState::Watching(ref mut match_) => {
+                draw_watching(&mut draw_handle, &ts, match_);
+                match message {
+                    Some(ServerMessage::Dealt { p1, p2 }) => {
+                        match_.p1 = Some(p1);
+                        match_.p2 = Some(p2);
+                        match_.p1_pick = None;
+                        match_.p2_pick = None;
+                    }
+                    Some(ServerMessage::Picked { player: 1, choice }) => {
+                        match_.p1_pick = Some(choice)
+                    }
+                    Some(ServerMessage::Picked { choice, .. }) => match_.p2_pick = Some(choice),
+                    Some(ServerMessage::Round {
+                        outcome,
+                        p1,
+                        p2,
+                        score,
+                    }) => {
+                        match_.score = score;
+                        match_.last = Some((outcome, p1, p2));
+                    }
+                    Some(ServerMessage::Timeout { score, .. }) => {
+                        match_.score = score;
+                        match_.last = None;
+                    }
+                    Some(ServerMessage::Left) => {
+                        println!("a player left room {}", match_.room);
+                        match_.score = MatchScore::new(match_.score.best_of);
+                        match_.p1 = None;
+                        match_.p2 = None;
+                    }
+                    // The room was closed, back to the lobby.
+                    Some(ServerMessage::Rooms { rooms }) => {
+                        gamestate = GameState::Lobby(rooms, 0);
+                    }
+                    Some(message) => println!("unexpected message: {:?}", message),
+                    None if draw_handle.is_key_pressed(KeyboardKey::KEY_BACKSPACE) => {
+                        connection.send(&ClientMessage::Unwatch);
+                        gamestate = GameState::JoiningRoom;
+                    }
+                    None => (),
+                }
+            }
             GameState::WaitingForChoices => {
                 draw_handle.draw_text("Waiting for Opponent ...", 10, 10, 10, Color::BLACK);
-                if let Some(choices) = get_message(&mut read_rx) {
-                    let (mine, theirs) = parse_choices(choices);
-                    gamestate = GameState::GotChoices(mine, theirs, 1);
+                match message {
+                    // The round is reset for the next opponent.
+                    Some(ServerMessage::Left) => {
+                        println!("opponent left, waiting for another one.");
+                        scores = (0, 0);
+                        connection.send(&ClientMessage::Ready);
+                    }
+                    Some(message) => println!("unexpected message: {:?}", message),
+                    None => (),
                 }
             }
             GameState::GotChoices(ref mine, ref theirs, hoover_index) => {
                 draw_choices(&mut draw_handle, &ts, mine, theirs, hoover_index);
+                if let Some(seconds) = countdown {
+                    draw_countdown(&mut draw_handle, "Time left:", seconds);
+                }
+                match message {
+                    // The server reset the room, these choices are gone.
+                    Some(ServerMessage::Left) => {
+                        println!("opponent left, waiting for another one.");
+                        scores = (0, 0);
+                        gamestate = GameState::WaitingForChoices;
+                        connection.send(&ClientMessage::Ready);
+                        continue;
+                    }
+                    Some(message) => println!("unexpected message: {:?}", message),
+                    None => (),
+                }
 
                 match handle_input(&mut draw_handle, mine, hoover_index) {
-                    (Some(selected), None) => {
-                        let msg = format!("selected:{}", selected);
-                        write_tx
-                            .unbounded_send(Message::Text(msg.to_string()))
-                            .unwrap();
+                    (Some(choice), None) => {
+                        connection.send(&ClientMessage::Selected { choice });
                         gamestate = GameState::WaitingForOtherSelected;
                     }
                     (None, Some(hoover_index)) => {
@@ -119,29 +557,37 @@ async fn main_loop(mut read_rx: ReadRx, write_tx: WriteTx) {
             }
             GameState::WaitingForOtherSelected => {
                 draw_handle.draw_text("Waiting for Opponent ...", 10, 10, 10, Color::BLACK);
-                if let Some(message) = get_message(&mut read_rx) {
-                    let (outcome, yours, theirs) = parse_outcome(message);
-                    gamestate = GameState::GotOutcome(outcome, yours, theirs);
+                match message {
+                    Some(ServerMessage::Left) => {
+                        println!("opponent left, waiting for another one.");
+                        scores = (0, 0);
+                        gamestate = GameState::WaitingForChoices;
+                        connection.send(&ClientMessage::Ready);
+                    }
+                    Some(message) => println!("unexpected message: {:?}", message),
+                    None => (),
                 }
             }
-            GameState::GotOutcome(ref outcome, ref yours, ref theirs) => {
-                draw_outcome(
-                    &mut draw_handle,
-                    outcome,
-                    yours.to_string(),
-                    theirs.to_string(),
-                );
-                if retry(&mut draw_handle) {
-                    match outcome {
-                        Outcome::Won => scores.0 += 1,
-                        Outcome::Lost => scores.1 += 1,
-                        _ => (),
+            GameState::GotOutcome(_, _, _, ref score) | GameState::GotTimeout(_, ref score) => {
+                match gamestate {
+                    GameState::GotOutcome(ref outcome, ref yours, ref theirs, _) => {
+                        draw_outcome(&mut draw_handle, outcome, yours, theirs, score)
+                    }
+                    GameState::GotTimeout(ref outcome, _) => {
+                        draw_timeout(&mut draw_handle, outcome, score)
+                    }
+                    _ => (),
+                }
+                if let Some(seconds) = countdown {
+                    draw_countdown(&mut draw_handle, "Next round in", seconds);
+                }
+                if retry(&mut draw_handle, score) {
+                    if score.winner().is_some() {
+                        scores = (0, 0);
                     }
 
                     gamestate = GameState::WaitingForChoices;
-                    write_tx
-                        .unbounded_send(Message::Text("ready:_".to_string()))
-                        .unwrap();
+                    connection.send(&ClientMessage::Ready);
                 }
             }
         }
@@ -150,25 +596,87 @@ async fn main_loop(mut read_rx: ReadRx, write_tx: WriteTx) {
     println!("done");
 }
 
-#[tokio::main]
-async fn main() -> Result<(), String> {
-    let addr = env::args()
-        .nth(1)
-        .unwrap_or_else(|| "ws://127.0.0.1:8080/".to_string());
-    let url = url::Url::parse(&addr).unwrap();
+// Flashcards on the chart, played alone without any server.
+fn quiz_loop(config: Config) {
+    let mut quiz = Quiz::new(config.ruleset());
+    let types = quiz.ruleset.types.clone();
+    let mut rng = rand::thread_rng();
+    let mut card = quiz.deal(&mut rng);
+    let mut picked: Vec<PokemonType> = vec![];
+    let mut hoover_index = 0;
+    let mut grade: Option<Grade> = None;
+
+    set_trace_log(TraceLogType::LOG_FATAL);
+    let (mut handle, thread) = raylib::init().size(640, 480).title("Hello, World").build();
+    handle.set_target_fps(60);
+    let ts = TextureStore::new(&mut handle, &thread, &PokemonType::ALL);
+
+    while !handle.window_should_close() {
+        let mut draw_handle = handle.begin_drawing(&thread);
+        draw_handle.clear_background(Color::WHITE);
+        draw_handle.draw_text(
+            &format!("{}/{}", quiz.perfect, quiz.asked),
+            615,
+            10,
+            10,
+            Color::BLACK,
+        );
+        draw_quiz(
+            &mut draw_handle,
+            &ts,
+            &card,
+            &types,
+            &picked,
+            hoover_index,
+            grade.as_ref(),
+        );
 
-    let (ws, _) = connect_async(url).await.expect("Failed to connect");
-    let (write, read) = ws.split();
+        if grade.is_some() {
+            if draw_handle.is_key_pressed(KeyboardKey::KEY_SPACE) {
+                card = quiz.deal(&mut rng);
+                picked.clear();
+                grade = None;
+            }
+            continue;
+        }
 
-    let (write_tx, write_rx) = mpsc::unbounded();
-    let (read_tx, read_rx) = mpsc::unbounded();
-    let read_handle = read.map(Ok).forward(read_tx);
-    let write_handle = write_rx.map(Ok).forward(write);
+        hoover_index = handle_grid_input(&mut draw_handle, types.len(), hoover_index);
+        if draw_handle.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            let type_ = types[hoover_index];
+            match picked.iter().position(|t| *t == type_) {
+                Some(index) => {
+                    picked.remove(index);
+                }
+                None => picked.push(type_),
+            }
+        }
+        if draw_handle.is_key_pressed(KeyboardKey::KEY_SPACE) {
+            grade = Some(quiz.submit(&card, &picked));
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), String> {
+    let config = Config::from_args(env::args().skip(1))?;
+    url::Url::parse(&config.url).map_err(|e| e.to_string())?;
 
-    spawn(main_loop(read_rx, write_tx));
+    if config.quiz {
+        quiz_loop(config);
+        return Ok(());
+    }
 
-    pin_mut!(read_handle, write_handle);
-    future::select(read_handle, write_handle).await;
+    let connection = if config.offline {
+        Connection::Offline(Box::new(Practice::new(
+            config.ruleset(),
+            config.bot.unwrap_or(StrategyKind::Greedy).build(),
+        )))
+    } else {
+        Connection::open(&config.url)
+            .await
+            .expect("Failed to connect")
+    };
+    main_loop(config, connection).await;
 
     Ok(())
 }
@@ -0,0 +1,6 @@
+pub mod chart;
+pub mod protocol;
+pub mod round;
+pub mod score;
+pub mod strategy;
+pub mod types;
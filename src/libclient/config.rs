@@ -0,0 +1,194 @@
+use typestrainer::{
+    chart::{Generation, Ruleset},
+    protocol::ClientMessage,
+    strategy::StrategyKind,
+};
+
+pub struct Config {
+    pub url: String,
+    pub generation: Option<u8>,
+    pub inverse: bool,
+    pub code: Option<String>,
+    pub password: Option<String>,
+    pub offline: bool,
+    pub quiz: bool,
+    pub bot: Option<StrategyKind>,
+}
+
+impl Config {
+    pub fn from_args(args: impl Iterator<Item = String>) -> Result<Config, String> {
+        let mut config = Config {
+            url: "ws://127.0.0.1:8080/".to_string(),
+            generation: None,
+            inverse: false,
+            code: None,
+            password: None,
+            offline: false,
+            quiz: false,
+            bot: None,
+        };
+        let mut args = args;
+
+        while let Some(arg) = args.next() {
+            match &*arg {
+                "--generation" => {
+                    let value = args.next().ok_or("--generation expects a number")?;
+                    config.generation = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|n| Generation::from_number(*n).is_some())
+                            .ok_or(format!("unknown generation '{}'", value))?,
+                    );
+                }
+                "--inverse" => config.inverse = true,
+                "--code" => {
+                    config.code = Some(args.next().ok_or("--code expects a room code")?);
+                }
+                "--password" => {
+                    config.password = Some(args.next().ok_or("--password expects a password")?);
+                }
+                "--offline" => config.offline = true,
+                "--quiz" => config.quiz = true,
+                "--bot" => {
+                    let value = args.next().ok_or("--bot expects a strategy")?;
+                    config.bot = Some(StrategyKind::from_name(&value).ok_or(format!(
+                        "unknown strategy '{}', expected {}",
+                        value,
+                        StrategyKind::NAMES
+                    ))?);
+                }
+                _ => config.url = arg,
+            }
+        }
+
+        Ok(config)
+    }
+
+    // Practice matches are played with the chart of the requested generation,
+    // inverted for an Inverse Battle.
+    pub fn ruleset(&self) -> Ruleset {
+        let generation = self.generation.and_then(Generation::from_number);
+        let ruleset = Ruleset::from_generation(generation.unwrap_or(Generation::Gen6));
+        if self.inverse {
+            ruleset.inverted()
+        } else {
+            ruleset
+        }
+    }
+
+    // The server picks the strategy of its bots unless one was given.
+    pub fn bot_message(&self) -> ClientMessage {
+        ClientMessage::Bot {
+            strategy: self.bot.map(|kind| kind.name().to_string()),
+        }
+    }
+
+    pub fn find_message(&self) -> ClientMessage {
+        ClientMessage::Find {
+            generation: self.generation,
+            inverse: self.inverse,
+        }
+    }
+
+    pub fn create_message(&self) -> ClientMessage {
+        ClientMessage::Create {
+            inverse: self.inverse,
+        }
+    }
+
+    pub fn private_message(&self) -> ClientMessage {
+        ClientMessage::CreatePrivate {
+            password: self.password.clone(),
+            inverse: self.inverse,
+        }
+    }
+
+    pub fn enter_message(&self, code: &str) -> ClientMessage {
+        ClientMessage::Enter {
+            code: code.to_string(),
+            password: self.password.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> impl Iterator<Item = String> + '_ {
+        line.split_whitespace().map(String::from)
+    }
+
+    #[test]
+    fn test_from_args() {
+        let config = Config::from_args(args("")).unwrap();
+        assert_eq!(config.url, "ws://127.0.0.1:8080/");
+        assert!(!config.offline);
+        assert!(!config.quiz);
+        assert_eq!(config.bot_message(), ClientMessage::Bot { strategy: None });
+        assert_eq!(config.ruleset().types.len(), 18);
+        assert_eq!(
+            config.find_message(),
+            ClientMessage::Find {
+                generation: None,
+                inverse: false
+            }
+        );
+        assert_eq!(
+            config.private_message(),
+            ClientMessage::CreatePrivate {
+                password: None,
+                inverse: false
+            }
+        );
+
+        let config = Config::from_args(args("ws://example.com:80 --generation 1")).unwrap();
+        assert_eq!(config.url, "ws://example.com:80");
+        assert_eq!(
+            config.find_message(),
+            ClientMessage::Find {
+                generation: Some(1),
+                inverse: false
+            }
+        );
+
+        let config = Config::from_args(args("--inverse")).unwrap();
+        assert_eq!(
+            config.create_message(),
+            ClientMessage::Create { inverse: true }
+        );
+
+        let config = Config::from_args(args("--code ABC234 --password secret")).unwrap();
+        assert_eq!(config.code.as_deref(), Some("ABC234"));
+        assert_eq!(
+            config.enter_message("ABC234"),
+            ClientMessage::Enter {
+                code: "ABC234".to_string(),
+                password: Some("secret".to_string())
+            }
+        );
+
+        let config = Config::from_args(args("--offline --generation 1 --bot nash")).unwrap();
+        assert!(config.offline);
+        assert_eq!(
+            config.bot_message(),
+            ClientMessage::Bot {
+                strategy: Some("nash".to_string())
+            }
+        );
+        assert_eq!(config.ruleset().types.len(), 15);
+        assert!(!config.ruleset().inverse);
+
+        let config = Config::from_args(args("--offline --inverse")).unwrap();
+        assert!(config.ruleset().inverse);
+
+        assert!(Config::from_args(args("--quiz")).unwrap().quiz);
+
+        assert!(Config::from_args(args("--generation")).is_err());
+        assert!(Config::from_args(args("--generation 12")).is_err());
+        assert!(Config::from_args(args("--code")).is_err());
+        assert!(Config::from_args(args("--bot")).is_err());
+        assert!(Config::from_args(args("--bot psychic")).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+use crate::libclient::practice::Practice;
+use futures_channel::{
+    mpsc,
+    mpsc::{UnboundedReceiver, UnboundedSender},
+};
+use futures_util::StreamExt;
+use std::time::{Duration, Instant};
+use tokio::spawn;
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{protocol::Message, Error as TungsteniteError},
+};
+use typestrainer::protocol::{decode, encode, ClientMessage, Feature, ServerMessage};
+
+type ReadRx = UnboundedReceiver<Result<Message, TungsteniteError>>;
+type WriteTx = UnboundedSender<Message>;
+
+// The server pings every few seconds, a longer silence means the connection
+// is dead.
+const SERVER_TIMEOUT: Duration = Duration::from_secs(15);
+
+pub enum Connection {
+    Remote {
+        read_rx: ReadRx,
+        write_tx: WriteTx,
+        closed: bool,
+        last_seen: Instant,
+    },
+    // A practice match played locally, nothing goes over the network.
+    Offline(Box<Practice>),
+}
+
+impl Connection {
+    // Connects to the server and says hello.
+    pub async fn open(url: &str) -> Result<Connection, TungsteniteError> {
+        let (ws, _) = connect_async(url).await?;
+        let (write, read) = ws.split();
+
+        let (write_tx, write_rx) = mpsc::unbounded();
+        let (read_tx, read_rx) = mpsc::unbounded();
+        spawn(read.map(Ok).forward(read_tx));
+        spawn(write_rx.map(Ok).forward(write));
+
+        let mut connection = Connection::Remote {
+            read_rx,
+            write_tx,
+            closed: false,
+            last_seen: Instant::now(),
+        };
+        connection.send(&ClientMessage::Hello {
+            client: "typestrainer-client".to_string(),
+            client_version: env!("CARGO_PKG_VERSION").to_string(),
+            features: Feature::ALL.to_vec(),
+        });
+        Ok(connection)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        match self {
+            Connection::Remote {
+                closed, last_seen, ..
+            } => *closed || last_seen.elapsed() > SERVER_TIMEOUT,
+            Connection::Offline(_) => false,
+        }
+    }
+
+    pub fn receive(&mut self) -> Option<ServerMessage> {
+        let (read_rx, closed, last_seen) = match self {
+            Connection::Remote {
+                read_rx,
+                closed,
+                last_seen,
+                ..
+            } => (read_rx, closed, last_seen),
+            Connection::Offline(practice) => return practice.receive(),
+        };
+        match read_rx.try_next() {
+            Ok(Some(Ok(msg))) => {
+                println!("{:?}", msg);
+                *last_seen = Instant::now();
+                match decode(&msg.to_string()) {
+                    Ok(message) => Some(message),
+                    Err(e) => {
+                        println!("invalid message: {}", e);
+                        None
+                    }
+                }
+            }
+            Ok(Some(Err(e))) => {
+                println!("connection error: {}", e);
+                *closed = true;
+                None
+            }
+            Ok(None) => {
+                *closed = true;
+                None
+            }
+            Err(_) => None,
+        }
+    }
+
+    pub fn send(&mut self, message: &ClientMessage) {
+        match self {
+            // Lost with the connection, the round is resumed after reconnecting.
+            Connection::Remote { write_tx, .. } => {
+                let _ = write_tx.unbounded_send(Message::Text(encode(message)));
+            }
+            Connection::Offline(practice) => practice.send(message),
+        }
+    }
+}
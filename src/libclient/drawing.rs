@@ -1,6 +1,18 @@
-use crate::Outcome;
-use crate::TextureStore;
+use crate::{
+    libclient::{
+        quiz::{Card, Grade},
+        state::Spectated,
+    },
+    TextureStore,
+};
 use raylib::prelude::*;
+use std::cmp::Ordering;
+use typestrainer::{
+    chart::Matchup,
+    protocol::{Outcome, RoomInfo},
+    score::MatchScore,
+    types::{format_choice, Choice, PokemonType},
+};
 
 fn tex_rec() -> Rectangle {
     Rectangle {
@@ -11,23 +23,110 @@ fn tex_rec() -> Rectangle {
     }
 }
 
-pub fn retry(draw_handle: &mut RaylibDrawHandle) -> bool {
-    draw_handle.draw_text("Press enter to play again.", 10, 10, 10, Color::BLACK);
+pub fn retry(draw_handle: &mut RaylibDrawHandle, score: &MatchScore) -> bool {
+    let text = if score.winner().is_some() {
+        "Press enter to start a new match."
+    } else {
+        "Press enter to play the next round."
+    };
+    draw_handle.draw_text(text, 10, 10, 10, Color::BLACK);
     draw_handle.is_key_pressed(KeyboardKey::KEY_ENTER)
 }
 
+// Listed above the open rooms in the lobby.
+pub const LOBBY_ENTRIES: [&str; 5] = [
+    "Find a match",
+    "Create a new room",
+    "Create a private room",
+    "Enter a room code",
+    "Play against a bot",
+];
+
+pub fn draw_lobby(
+    draw_handle: &mut RaylibDrawHandle,
+    rooms: &[RoomInfo],
+    i: usize,
+    notice: Option<&str>,
+) {
+    draw_handle.draw_text(
+        "Pick a room and press enter, or W to watch it.",
+        10,
+        10,
+        10,
+        Color::BLACK,
+    );
+    if let Some(notice) = notice {
+        draw_handle.draw_text(notice, 10, 30, 10, Color::RED);
+    }
+
+    let entries = LOBBY_ENTRIES
+        .iter()
+        .map(|entry| entry.to_string())
+        .chain(rooms.iter().map(|room| {
+            let name = match room.inverse {
+                true => format!("Inverse room {}", room.id),
+                false => format!("Room {}", room.id),
+            };
+            match room.spectators {
+                0 => format!("{} ({}/2)", name, room.players),
+                n => format!("{} ({}/2, {} watching)", name, room.players, n),
+            }
+        }));
+    for (index, entry) in entries.enumerate() {
+        let color = if i == index {
+            Color::BLACK
+        } else {
+            Color::GRAY
+        };
+        draw_handle.draw_text(&entry, 200, 60 + 30 * index as i32, 20, color);
+    }
+}
+
+pub fn draw_code_entry(draw_handle: &mut RaylibDrawHandle, code: &str) {
+    draw_handle.draw_text(
+        "Type the room code and press enter, backspace on empty to go back.",
+        10,
+        10,
+        10,
+        Color::BLACK,
+    );
+    draw_handle.draw_text(&format!("Code: {}_", code), 200, 240, 20, Color::BLACK);
+}
+
+pub fn draw_queued(draw_handle: &mut RaylibDrawHandle, position: usize) {
+    draw_handle.draw_text(
+        "Waiting for a match, press backspace to cancel.",
+        10,
+        10,
+        10,
+        Color::BLACK,
+    );
+    draw_handle.draw_text(
+        &format!("Position in queue: {}", position),
+        200,
+        240,
+        20,
+        Color::BLACK,
+    );
+}
+
 pub fn draw_outcome(
     draw_handle: &mut RaylibDrawHandle,
     outcome: &Outcome,
-    yours: String,
-    theirs: String,
+    yours: &Matchup,
+    theirs: &Matchup,
+    score: &MatchScore,
 ) {
-    let (yours, theirs) = (&*yours, &*theirs);
+    let (your_choice, their_choice) = (
+        format_choice(&yours.attacker),
+        format_choice(&theirs.attacker),
+    );
+    let (yours_text, theirs_text) = (&*your_choice, &*their_choice);
     match outcome {
         Outcome::Won => {
             draw_handle.draw_text("You won !", 320, 240, 24, Color::BLACK);
             draw_handle.draw_text(
-                &*format!("{} beats {}", yours, theirs),
+                &format!("{} beats {}", yours_text, theirs_text),
                 200,
                 280,
                 20,
@@ -37,7 +136,7 @@ pub fn draw_outcome(
         Outcome::Lost => {
             draw_handle.draw_text("You lost :/", 320, 240, 24, Color::BLACK);
             draw_handle.draw_text(
-                &*format!("{} beats {}", theirs, yours),
+                &format!("{} beats {}", theirs_text, yours_text),
                 200,
                 280,
                 20,
@@ -47,7 +146,7 @@ pub fn draw_outcome(
         Outcome::Tie => {
             draw_handle.draw_text("Its a tie ...", 320, 240, 24, Color::BLACK);
             draw_handle.draw_text(
-                &*format!("{} == {}", theirs, yours),
+                &format!("{} == {}", theirs_text, yours_text),
                 200,
                 280,
                 20,
@@ -55,50 +154,247 @@ pub fn draw_outcome(
             );
         }
     };
+
+    draw_handle.draw_text(
+        &format!("You: {}", yours.explain()),
+        10,
+        340,
+        10,
+        Color::DARKGRAY,
+    );
+    draw_handle.draw_text(
+        &format!("Them: {}", theirs.explain()),
+        10,
+        360,
+        10,
+        Color::DARKGRAY,
+    );
+
+    draw_match_result(draw_handle, score);
+}
+
+pub fn draw_timeout(draw_handle: &mut RaylibDrawHandle, outcome: &Outcome, score: &MatchScore) {
+    let text = match outcome {
+        Outcome::Won => "Your opponent ran out of time !",
+        Outcome::Lost => "You ran out of time :/",
+        Outcome::Tie => "Nobody selected in time ...",
+    };
+    draw_handle.draw_text(text, 200, 240, 24, Color::BLACK);
+    draw_match_result(draw_handle, score);
+}
+
+pub fn draw_countdown(draw_handle: &mut RaylibDrawHandle, text: &str, seconds: u64) {
+    draw_handle.draw_text(
+        &format!("{} {}s", text, seconds),
+        10,
+        30,
+        10,
+        Color::DARKGRAY,
+    );
+}
+
+fn draw_match_result(draw_handle: &mut RaylibDrawHandle, score: &MatchScore) {
+    draw_handle.draw_text(
+        &format!("Round {} of best of {}", score.round, score.best_of),
+        10,
+        380,
+        10,
+        Color::DARKGRAY,
+    );
+    match score.winner() {
+        Some(Ordering::Greater) => {
+            draw_handle.draw_text(
+                &format!("You won the match {}-{} !", score.wins.0, score.wins.1),
+                200,
+                420,
+                20,
+                Color::BLACK,
+            );
+        }
+        Some(_) => {
+            draw_handle.draw_text(
+                &format!("You lost the match {}-{}", score.wins.0, score.wins.1),
+                200,
+                420,
+                20,
+                Color::BLACK,
+            );
+        }
+        None => (),
+    }
+}
+
+fn draw_choice(
+    draw_handle: &mut RaylibDrawHandle,
+    ts: &TextureStore,
+    choice: &[PokemonType],
+    x: f32,
+    y: f32,
+    tint: Color,
+) {
+    let width = tex_rec().width;
+    let mut x = x - (choice.len() - 1) as f32 * (width / 2.0 + 2.0);
+    for type_ in choice {
+        draw_handle.draw_texture_rec(&ts.textures[type_], tex_rec(), Vector2 { x, y }, tint);
+        x += width + 4.0;
+    }
 }
 
 pub fn draw_choices(
     draw_handle: &mut RaylibDrawHandle,
     ts: &TextureStore,
-    mine: &[String],
-    theirs: &[String],
+    mine: &[Choice],
+    theirs: &[Choice],
     i: usize,
 ) {
-    draw_handle.draw_texture_rec(
-        &ts.textures[&*mine[0]],
-        tex_rec(),
-        Vector2 { x: 50.0, y: 280.0 },
-        if i == 0 { Color::GRAY } else { Color::WHITE },
-    );
-    draw_handle.draw_texture_rec(
-        &ts.textures[&*mine[1]],
-        tex_rec(),
-        Vector2 { x: 285.0, y: 280.0 },
-        if i == 1 { Color::GRAY } else { Color::WHITE },
-    );
-    draw_handle.draw_texture_rec(
-        &ts.textures[&*mine[2]],
-        tex_rec(),
-        Vector2 { x: 520.0, y: 280.0 },
-        if i == 2 { Color::GRAY } else { Color::WHITE },
-    );
+    for (index, (choice, x)) in mine.iter().zip(&[50.0, 285.0, 520.0]).enumerate() {
+        let tint = if i == index {
+            Color::GRAY
+        } else {
+            Color::WHITE
+        };
+        draw_choice(draw_handle, ts, choice, *x, 280.0, tint);
+    }
 
-    draw_handle.draw_texture_rec(
-        &ts.textures[&*theirs[0]],
-        tex_rec(),
-        Vector2 { x: 50.0, y: 50.0 },
-        Color::WHITE,
+    for (choice, x) in theirs.iter().zip(&[50.0, 285.0, 520.0]) {
+        draw_choice(draw_handle, ts, choice, *x, 50.0, Color::WHITE);
+    }
+}
+
+pub fn draw_watching(draw_handle: &mut RaylibDrawHandle, ts: &TextureStore, match_: &Spectated) {
+    draw_handle.draw_text(
+        &format!("Watching room {}, press backspace to go back.", match_.room),
+        10,
+        10,
+        10,
+        Color::BLACK,
     );
-    draw_handle.draw_texture_rec(
-        &ts.textures[&*theirs[1]],
-        tex_rec(),
-        Vector2 { x: 285.0, y: 50.0 },
-        Color::WHITE,
+
+    let rows = [
+        (&match_.p2, &match_.p2_pick, 50.0),
+        (&match_.p1, &match_.p1_pick, 280.0),
+    ];
+    for (choices, pick, y) in rows.iter() {
+        for (choice, x) in choices.iter().flatten().zip(&[50.0, 285.0, 520.0]) {
+            let tint = if pick.as_ref() == Some(choice) {
+                Color::GRAY
+            } else {
+                Color::WHITE
+            };
+            draw_choice(draw_handle, ts, choice, *x, *y, tint);
+        }
+    }
+    draw_handle.draw_text("Player 2", 10, 30, 10, Color::DARKGRAY);
+    draw_handle.draw_text("Player 1", 10, 260, 10, Color::DARKGRAY);
+
+    let score = &match_.score;
+    draw_handle.draw_text(
+        &format!(
+            "{}-{}, round {} of best of {}",
+            score.wins.0, score.wins.1, score.round, score.best_of
+        ),
+        10,
+        380,
+        10,
+        Color::DARKGRAY,
     );
-    draw_handle.draw_texture_rec(
-        &ts.textures[&*theirs[2]],
-        tex_rec(),
-        Vector2 { x: 520.0, y: 50.0 },
-        Color::WHITE,
+
+    if let Some((outcome, p1, p2)) = &match_.last {
+        let text = match outcome {
+            Outcome::Won => format!(
+                "Player 1 won the round with {}",
+                format_choice(&p1.attacker)
+            ),
+            Outcome::Lost => format!(
+                "Player 2 won the round with {}",
+                format_choice(&p2.attacker)
+            ),
+            Outcome::Tie => "The round is a tie".to_string(),
+        };
+        draw_handle.draw_text(&text, 200, 420, 20, Color::BLACK);
+        draw_handle.draw_text(
+            &format!("P1: {}", p1.explain()),
+            10,
+            340,
+            10,
+            Color::DARKGRAY,
+        );
+        draw_handle.draw_text(
+            &format!("P2: {}", p2.explain()),
+            10,
+            360,
+            10,
+            Color::DARKGRAY,
+        );
+    }
+}
+
+// Types of the quiz are laid out in a grid, six to a row.
+pub const QUIZ_COLUMNS: usize = 6;
+
+pub fn draw_quiz(
+    draw_handle: &mut RaylibDrawHandle,
+    ts: &TextureStore,
+    card: &Card,
+    types: &[PokemonType],
+    picked: &[PokemonType],
+    i: usize,
+    grade: Option<&Grade>,
+) {
+    let help = match grade {
+        Some(_) => "Press space for the next card.",
+        None => "Press enter to pick a type, space to check your answer.",
+    };
+    draw_handle.draw_text(help, 10, 10, 10, Color::BLACK);
+    draw_choice(draw_handle, ts, &card.defender, 285.0, 40.0, Color::WHITE);
+    draw_handle.draw_text(
+        &card.question.text(&card.defender),
+        10,
+        130,
+        20,
+        Color::BLACK,
     );
+
+    for (index, type_) in types.iter().enumerate() {
+        let x = 50.0 + (index % QUIZ_COLUMNS) as f32 * 90.0;
+        let y = 170.0 + (index / QUIZ_COLUMNS) as f32 * 85.0;
+        let tint = if grade.is_none() && i == index {
+            Color::GRAY
+        } else {
+            Color::WHITE
+        };
+        draw_handle.draw_texture_rec(&ts.textures[type_], tex_rec(), Vector2 { x, y }, tint);
+
+        // Picks are framed, then graded once checked: right, wrong or missed.
+        let frame = match grade {
+            Some(grade) if grade.right.contains(type_) => Some(Color::GREEN),
+            Some(grade) if grade.wrong.contains(type_) => Some(Color::RED),
+            Some(grade) if grade.missed.contains(type_) => Some(Color::ORANGE),
+            None if picked.contains(type_) => Some(Color::BLUE),
+            _ => None,
+        };
+        if let Some(color) = frame {
+            let rec = Rectangle {
+                x: x - 3.0,
+                y: y - 3.0,
+                width: tex_rec().width + 6.0,
+                height: tex_rec().height + 6.0,
+            };
+            draw_handle.draw_rectangle_lines_ex(rec, 3, color);
+        }
+    }
+
+    if let Some(grade) = grade {
+        let text = if grade.is_perfect() {
+            "Right !".to_string()
+        } else {
+            let answer: Vec<PokemonType> = types
+                .iter()
+                .filter(|t| grade.right.contains(t) || grade.missed.contains(t))
+                .cloned()
+                .collect();
+            format!("The answer was {}", format_choice(&answer))
+        };
+        draw_handle.draw_text(&text, 10, 430, 20, Color::BLACK);
+    }
 }
@@ -1,4 +1,7 @@
+pub mod config;
+pub mod connection;
 pub mod drawing;
+pub mod practice;
+pub mod quiz;
 pub mod state;
 pub mod textures;
-pub mod utils;
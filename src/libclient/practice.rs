@@ -0,0 +1,185 @@
+use rand::{rngs::ThreadRng, seq::SliceRandom};
+use std::{collections::VecDeque, time::Instant};
+use typestrainer::{
+    chart::Ruleset,
+    protocol::{ClientMessage, ErrorCode, Outcome, ProtocolError, ServerMessage},
+    round::{deal, play, BEST_OF, DUAL_TYPE_ODDS, READY_TIME, ROUND_TIME},
+    score::MatchScore,
+    strategy::Strategy,
+    types::Choice,
+};
+
+// Plays the server side of a match against a built-in bot, without any
+// network. Answers are queued until the client receives them.
+pub struct Practice {
+    ruleset: Ruleset,
+    bot: Box<dyn Strategy>,
+    score: MatchScore,
+    rng: ThreadRng,
+    // The player's choices, the bot's and the bot's pick.
+    dealt: Option<(Vec<Choice>, Vec<Choice>, Choice)>,
+    deadline: Option<Instant>,
+    inbox: VecDeque<ServerMessage>,
+}
+
+impl Practice {
+    pub fn new(ruleset: Ruleset, bot: Box<dyn Strategy>) -> Practice {
+        let mut inbox = VecDeque::new();
+        inbox.push_back(ServerMessage::Joined {
+            room: 0,
+            types: ruleset.types.clone(),
+            session: String::new(),
+        });
+        Practice {
+            ruleset,
+            bot,
+            score: MatchScore::new(BEST_OF),
+            rng: rand::thread_rng(),
+            dealt: None,
+            deadline: None,
+            inbox,
+        }
+    }
+
+    pub fn receive(&mut self) -> Option<ServerMessage> {
+        self.tick();
+        self.inbox.pop_front()
+    }
+
+    pub fn send(&mut self, message: &ClientMessage) {
+        match message {
+            ClientMessage::Ready if self.dealt.is_none() => self.deal(),
+            ClientMessage::Ready => self.error(ErrorCode::AlreadyReady, "choices already dealt"),
+            ClientMessage::Selected { choice } => match self.dealt {
+                None => self.error(ErrorCode::ChoicesNotDealt, "choices not dealt yet"),
+                Some((ref yours, _, _)) if !yours.contains(choice) => {
+                    self.error(ErrorCode::InvalidChoice, "choice was not dealt")
+                }
+                Some(_) => self.resolve(choice.clone()),
+            },
+            // Nothing else makes sense without a server.
+            message => println!("ignored offline: {:?}", message),
+        }
+    }
+
+    // Applies the server timers: a random choice past the round deadline,
+    // the next round past the ready one.
+    fn tick(&mut self) {
+        match self.deadline {
+            Some(deadline) if deadline <= Instant::now() => (),
+            _ => return,
+        }
+        match self.dealt {
+            Some((ref yours, _, _)) => {
+                let choice = yours.choose(&mut self.rng).cloned().unwrap();
+                self.resolve(choice);
+            }
+            None => self.deal(),
+        }
+    }
+
+    fn deal(&mut self) {
+        let types = &self.ruleset.types;
+        let yours = deal(&mut self.rng, types, DUAL_TYPE_ODDS);
+        let theirs = deal(&mut self.rng, types, DUAL_TYPE_ODDS);
+        let pick = self.bot.pick(&self.ruleset.chart, &theirs, &yours);
+        self.inbox.push_back(ServerMessage::Choices {
+            yours: yours.clone(),
+            theirs: theirs.clone(),
+            time: ROUND_TIME.as_secs(),
+        });
+        self.dealt = Some((yours, theirs, pick));
+        self.deadline = Some(Instant::now() + ROUND_TIME);
+    }
+
+    fn resolve(&mut self, choice: Choice) {
+        let (hand, bot_hand, pick) = self.dealt.take().unwrap();
+        let (result, yours, theirs) = play(&self.ruleset.chart, &choice, &pick);
+        self.bot
+            .observe(&self.ruleset.chart, &bot_hand, &hand, &choice);
+        self.score.record(result);
+        self.inbox.push_back(ServerMessage::Outcome {
+            outcome: Outcome::from(result),
+            yours,
+            theirs,
+            score: self.score,
+            next_round: READY_TIME.as_secs(),
+        });
+        if self.score.winner().is_some() {
+            self.score = MatchScore::new(BEST_OF);
+        }
+        self.deadline = Some(Instant::now() + READY_TIME);
+    }
+
+    fn error(&mut self, code: ErrorCode, message: &str) {
+        self.inbox
+            .push_back(ServerMessage::Error(ProtocolError::new(code, message)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use typestrainer::{chart::Generation, strategy::StrategyKind, types::PokemonType::*};
+
+    #[test]
+    fn test_practice() {
+        let mut practice = Practice::new(
+            Ruleset::from_generation(Generation::Gen1),
+            StrategyKind::Random.build(),
+        );
+        assert!(matches!(
+            practice.receive(),
+            Some(ServerMessage::Joined { room: 0, .. })
+        ));
+        assert_eq!(practice.receive(), None);
+
+        practice.send(&ClientMessage::Selected {
+            choice: vec![Normal],
+        });
+        assert!(matches!(
+            practice.receive(),
+            Some(ServerMessage::Error(ProtocolError {
+                code: ErrorCode::ChoicesNotDealt,
+                ..
+            }))
+        ));
+
+        practice.send(&ClientMessage::Ready);
+        let yours = match practice.receive() {
+            Some(ServerMessage::Choices { yours, time, .. }) => {
+                assert_eq!(time, ROUND_TIME.as_secs());
+                yours
+            }
+            message => panic!("unexpected message: {:?}", message),
+        };
+        practice.send(&ClientMessage::Ready);
+        assert!(matches!(
+            practice.receive(),
+            Some(ServerMessage::Error(ProtocolError {
+                code: ErrorCode::AlreadyReady,
+                ..
+            }))
+        ));
+
+        practice.send(&ClientMessage::Selected {
+            choice: yours[0].clone(),
+        });
+        match practice.receive() {
+            Some(ServerMessage::Outcome {
+                yours: y, score, ..
+            }) => {
+                assert_eq!(y.attacker, yours[0]);
+                assert_eq!(score.round, 1);
+            }
+            message => panic!("unexpected message: {:?}", message),
+        }
+
+        // The ready deadline deals the next round.
+        practice.deadline = Some(Instant::now());
+        assert!(matches!(
+            practice.receive(),
+            Some(ServerMessage::Choices { .. })
+        ));
+    }
+}
@@ -0,0 +1,193 @@
+use rand::{seq::SliceRandom, Rng};
+use typestrainer::{
+    chart::{choice_effectiveness, Ruleset},
+    round::DUAL_TYPE_ODDS,
+    types::{format_choice, Choice, PokemonType},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Question {
+    SuperEffective,
+    Resisted,
+    NoEffect,
+}
+
+impl Question {
+    pub const ALL: [Question; 3] = [
+        Question::SuperEffective,
+        Question::Resisted,
+        Question::NoEffect,
+    ];
+
+    fn matches(&self, multiplier: f32) -> bool {
+        match self {
+            Question::SuperEffective => multiplier > 1.0,
+            Question::Resisted => multiplier > 0.0 && multiplier < 1.0,
+            Question::NoEffect => multiplier == 0.0,
+        }
+    }
+
+    pub fn text(&self, defender: &[PokemonType]) -> String {
+        let defender = format_choice(defender);
+        match self {
+            Question::SuperEffective => {
+                format!("Which types are super effective against {} ?", defender)
+            }
+            Question::Resisted => format!("Which types does {} resist ?", defender),
+            Question::NoEffect => format!("Which types have no effect on {} ?", defender),
+        }
+    }
+}
+
+pub struct Card {
+    pub defender: Choice,
+    pub question: Question,
+}
+
+impl Card {
+    // The attacking types answering the question, in the order of the ruleset.
+    pub fn answer(&self, ruleset: &Ruleset) -> Vec<PokemonType> {
+        ruleset
+            .types
+            .iter()
+            .filter(|t| {
+                let multiplier = choice_effectiveness(&ruleset.chart, &[**t], &self.defender);
+                self.question.matches(multiplier)
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Grade {
+    pub right: Vec<PokemonType>,
+    pub wrong: Vec<PokemonType>,
+    pub missed: Vec<PokemonType>,
+}
+
+impl Grade {
+    pub fn new(answer: &[PokemonType], picked: &[PokemonType]) -> Grade {
+        let (right, wrong) = picked.iter().partition(|t| answer.contains(t));
+        Grade {
+            right,
+            wrong,
+            missed: answer
+                .iter()
+                .filter(|t| !picked.contains(t))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn is_perfect(&self) -> bool {
+        self.wrong.is_empty() && self.missed.is_empty()
+    }
+}
+
+// Flashcards drilling the chart: a defending type, and the attacking types
+// to find for it.
+pub struct Quiz {
+    pub ruleset: Ruleset,
+    pub asked: u32,
+    pub perfect: u32,
+}
+
+impl Quiz {
+    pub fn new(ruleset: Ruleset) -> Quiz {
+        Quiz {
+            ruleset,
+            asked: 0,
+            perfect: 0,
+        }
+    }
+
+    // Cards without any answer are dealt again, "none" makes a poor
+    // flashcard.
+    pub fn deal(&self, rng: &mut impl Rng) -> Card {
+        loop {
+            let size = if rng.gen_bool(DUAL_TYPE_ODDS) { 2 } else { 1 };
+            let card = Card {
+                defender: self
+                    .ruleset
+                    .types
+                    .choose_multiple(rng, size)
+                    .cloned()
+                    .collect(),
+                question: *Question::ALL.choose(rng).unwrap(),
+            };
+            if !card.answer(&self.ruleset).is_empty() {
+                return card;
+            }
+        }
+    }
+
+    pub fn submit(&mut self, card: &Card, picked: &[PokemonType]) -> Grade {
+        let grade = Grade::new(&card.answer(&self.ruleset), picked);
+        self.asked += 1;
+        if grade.is_perfect() {
+            self.perfect += 1;
+        }
+        grade
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use typestrainer::{chart::Generation, types::PokemonType::*};
+
+    #[test]
+    fn test_answer() {
+        let ruleset = Ruleset::from_generation(Generation::Gen6);
+        let card = |defender: &[PokemonType], question| Card {
+            defender: defender.to_vec(),
+            question,
+        };
+        assert_eq!(
+            card(&[Ghost], Question::NoEffect).answer(&ruleset),
+            vec![Fighting, Normal]
+        );
+        assert_eq!(
+            card(&[Water, Ground], Question::SuperEffective).answer(&ruleset),
+            vec![Grass]
+        );
+        assert_eq!(
+            card(&[Fire], Question::Resisted).answer(&ruleset),
+            vec![Bug, Fairy, Fire, Grass, Ice, Steel]
+        );
+
+        // Gen 1 Psychic has no weakness to Dark, which does not exist yet.
+        let gen1 = Ruleset::from_generation(Generation::Gen1);
+        assert_eq!(
+            card(&[Psychic], Question::SuperEffective).answer(&gen1),
+            vec![Bug]
+        );
+    }
+
+    #[test]
+    fn test_submit() {
+        let mut quiz = Quiz::new(Ruleset::from_generation(Generation::Gen6));
+        let card = Card {
+            defender: vec![Ghost],
+            question: Question::NoEffect,
+        };
+        let grade = quiz.submit(&card, &[Normal, Psychic]);
+        assert_eq!(
+            grade,
+            Grade {
+                right: vec![Normal],
+                wrong: vec![Psychic],
+                missed: vec![Fighting],
+            }
+        );
+        assert!(quiz.submit(&card, &[Fighting, Normal]).is_perfect());
+        assert_eq!((quiz.perfect, quiz.asked), (1, 2));
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..20 {
+            let card = quiz.deal(&mut rng);
+            assert!(!card.answer(&quiz.ruleset).is_empty());
+        }
+    }
+}
@@ -1,12 +1,31 @@
-pub enum Outcome {
-    Won,
-    Lost,
-    Tie,
-}
+use typestrainer::{
+    chart::Matchup,
+    protocol::{Outcome, RoomInfo},
+    score::MatchScore,
+    types::Choice,
+};
 
 pub enum GameState {
+    Lobby(Vec<RoomInfo>, usize),
+    EnteringCode(String),
+    Queued(usize),
+    JoiningRoom,
+    Resuming,
     WaitingForChoices,
-    GotChoices(Vec<String>, Vec<String>, usize),
+    GotChoices(Vec<Choice>, Vec<Choice>, usize),
     WaitingForOtherSelected,
-    GotOutcome(Outcome, String, String),
+    GotOutcome(Outcome, Matchup, Matchup, MatchScore),
+    GotTimeout(Outcome, MatchScore),
+    Watching(Spectated),
+}
+
+// What a spectator knows of the match, updated from the live feed.
+pub struct Spectated {
+    pub room: u32,
+    pub score: MatchScore,
+    pub p1: Option<Vec<Choice>>,
+    pub p2: Option<Vec<Choice>>,
+    pub p1_pick: Option<Choice>,
+    pub p2_pick: Option<Choice>,
+    pub last: Option<(Outcome, Matchup, Matchup)>,
 }
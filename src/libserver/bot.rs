@@ -0,0 +1,132 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::atomic::{AtomicU32, Ordering},
+};
+use typestrainer::{
+    chart::Ruleset,
+    protocol::{ClientMessage, ServerMessage},
+    strategy::Strategy,
+    types::Choice,
+};
+
+// Bots are addressed in 0.0.0.0/8, which no connection comes from.
+static NEXT_BOT: AtomicU32 = AtomicU32::new(1);
+
+pub fn is_bot(addr: SocketAddr) -> bool {
+    matches!(addr.ip(), IpAddr::V4(ip) if ip.octets()[0] == 0)
+}
+
+pub enum Reaction {
+    Wait,
+    Send(ClientMessage),
+    // Sent after the thinking delay.
+    Think(ClientMessage),
+    Leave,
+}
+
+// A player of the server. It answers the messages a client gets with the
+// messages a client would send, so that it goes through the same rules.
+pub struct Bot {
+    pub addr: SocketAddr,
+    strategy: Box<dyn Strategy>,
+    // Its hand and the opponent's, of the current round.
+    hands: Option<(Vec<Choice>, Vec<Choice>)>,
+}
+
+impl Bot {
+    pub fn new(strategy: Box<dyn Strategy>) -> Bot {
+        let id = NEXT_BOT.fetch_add(1, Ordering::Relaxed);
+        Bot {
+            addr: SocketAddr::from((Ipv4Addr::from(id & 0x00ff_ffff), 0)),
+            strategy,
+            hands: None,
+        }
+    }
+
+    // Plays with the ruleset of its room, if still in one.
+    pub fn react(&mut self, message: ServerMessage, ruleset: Option<&Ruleset>) -> Reaction {
+        match (message, ruleset) {
+            (ServerMessage::Joined { .. }, _) => Reaction::Send(ClientMessage::Ready),
+            (ServerMessage::Choices { yours, theirs, .. }, Some(ruleset)) => {
+                let choice = self.strategy.pick(&ruleset.chart, &yours, &theirs);
+                self.hands = Some((yours, theirs));
+                Reaction::Think(ClientMessage::Selected { choice })
+            }
+            (ServerMessage::Outcome { theirs, .. }, Some(ruleset)) => {
+                if let Some((yours, their_hand)) = self.hands.take() {
+                    self.strategy
+                        .observe(&ruleset.chart, &yours, &their_hand, &theirs.attacker);
+                }
+                Reaction::Send(ClientMessage::Ready)
+            }
+            (ServerMessage::Timeout { .. }, _) => {
+                self.hands = None;
+                Reaction::Send(ClientMessage::Ready)
+            }
+            // Bots only play against the player they were seated for.
+            (ServerMessage::Left, _) | (ServerMessage::Forfeit { .. }, _) => Reaction::Leave,
+            _ => Reaction::Wait,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use typestrainer::{
+        chart::{Generation, Matchup},
+        protocol::Outcome,
+        score::MatchScore,
+        strategy::StrategyKind,
+        types::PokemonType::*,
+    };
+
+    #[test]
+    fn test_react() {
+        let ruleset = Ruleset::from_generation(Generation::Gen6);
+        let mut bot = Bot::new(StrategyKind::Greedy.build());
+        assert_ne!(bot.addr, Bot::new(StrategyKind::Random.build()).addr);
+        assert!(is_bot(bot.addr));
+        assert!(!is_bot(SocketAddr::from(([127, 0, 0, 1], 0))));
+
+        let joined = ServerMessage::Joined {
+            room: 1,
+            types: ruleset.types.clone(),
+            session: String::new(),
+        };
+        assert!(matches!(
+            bot.react(joined, Some(&ruleset)),
+            Reaction::Send(ClientMessage::Ready)
+        ));
+
+        let choices = ServerMessage::Choices {
+            yours: vec![vec![Normal], vec![Water], vec![Grass]],
+            theirs: vec![vec![Fire], vec![Ground]],
+            time: 20,
+        };
+        match bot.react(choices.clone(), None) {
+            Reaction::Wait => (),
+            _ => panic!("picked without a ruleset"),
+        }
+        match bot.react(choices, Some(&ruleset)) {
+            Reaction::Think(ClientMessage::Selected { choice }) => assert_eq!(choice, vec![Water]),
+            _ => panic!("no choice selected"),
+        }
+
+        let outcome = ServerMessage::Outcome {
+            outcome: Outcome::Won,
+            yours: Matchup::new(&ruleset.chart, &[Water], &[Fire]),
+            theirs: Matchup::new(&ruleset.chart, &[Fire], &[Water]),
+            score: MatchScore::new(3),
+            next_round: 30,
+        };
+        assert!(matches!(
+            bot.react(outcome, Some(&ruleset)),
+            Reaction::Send(ClientMessage::Ready)
+        ));
+        assert!(matches!(
+            bot.react(ServerMessage::Left, Some(&ruleset)),
+            Reaction::Leave
+        ));
+    }
+}
@@ -0,0 +1,193 @@
+use serde::Deserialize;
+use std::{collections::BTreeMap, fs};
+use typestrainer::{
+    chart::{effectiveness, insert_row, Chart, Ruleset},
+    types::PokemonType,
+};
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChartRow {
+    #[serde(default)]
+    pub super_effective: Vec<String>,
+    #[serde(default)]
+    pub not_very_effective: Vec<String>,
+    #[serde(default)]
+    pub no_effect: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChartFile {
+    pub types: Vec<String>,
+    #[serde(flatten)]
+    pub rows: BTreeMap<String, ChartRow>,
+}
+
+// Deals draw six distinct types, three choices of up to two types.
+const MIN_TYPES: usize = 6;
+
+fn parse_types(names: &[String]) -> Result<Vec<PokemonType>, String> {
+    names.iter().map(|name| name.parse()).collect()
+}
+
+impl ChartFile {
+    pub fn load(path: &str) -> Result<ChartFile, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        ChartFile::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn parse(text: &str) -> Result<ChartFile, String> {
+        toml::from_str(text).map_err(|e| e.to_string())
+    }
+
+    pub fn from_ruleset(ruleset: &Ruleset) -> ChartFile {
+        let mut rows = BTreeMap::new();
+
+        for attacker in ruleset.types.iter() {
+            let mut row = ChartRow::default();
+            for defender in ruleset.types.iter() {
+                let multiplier = effectiveness(&ruleset.chart, *attacker, *defender);
+                let list = if multiplier == 0.0 {
+                    &mut row.no_effect
+                } else if multiplier < 1.0 {
+                    &mut row.not_very_effective
+                } else if multiplier > 1.0 {
+                    &mut row.super_effective
+                } else {
+                    continue;
+                };
+                list.push(defender.to_string());
+            }
+            rows.insert(attacker.to_string(), row);
+        }
+
+        ChartFile {
+            types: ruleset.types.iter().map(|t| t.to_string()).collect(),
+            rows,
+        }
+    }
+
+    pub fn to_ruleset(&self) -> Result<Ruleset, String> {
+        let types = parse_types(&self.types)?;
+        for (i, name) in types.iter().enumerate() {
+            if types[..i].contains(name) {
+                return Err(format!("'{}' is listed twice in the type list", name));
+            }
+        }
+        if types.len() < MIN_TYPES {
+            return Err(format!(
+                "a chart needs at least {} types, found {}",
+                MIN_TYPES,
+                types.len()
+            ));
+        }
+        let mut chart = Chart::new();
+
+        for attacker in types.iter() {
+            let row = self.rows.get(attacker.name()).cloned().unwrap_or_default();
+            insert_row(
+                &mut chart,
+                *attacker,
+                &parse_types(&row.super_effective)?,
+                &parse_types(&row.not_very_effective)?,
+                &parse_types(&row.no_effect)?,
+            );
+        }
+
+        for (attacker, row) in self.rows.iter() {
+            for name in Some(attacker)
+                .into_iter()
+                .chain(&row.super_effective)
+                .chain(&row.not_very_effective)
+                .chain(&row.no_effect)
+            {
+                if !types.iter().any(|t| t.name() == name) {
+                    return Err(format!("'{}' is not in the type list", name));
+                }
+            }
+        }
+
+        Ok(Ruleset {
+            types,
+            chart,
+            inverse: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use typestrainer::{chart::Generation, types::PokemonType::*};
+
+    #[test]
+    fn test_parse() {
+        let file = ChartFile::parse(
+            r#"
+            types = ["fire", "grass", "ghost", "normal", "water", "ice"]
+
+            [fire]
+            super_effective = ["grass"]
+            not_very_effective = ["fire"]
+
+            [normal]
+            no_effect = ["ghost"]
+            "#,
+        )
+        .unwrap();
+        let ruleset = file.to_ruleset().unwrap();
+
+        assert_eq!(ruleset.types, vec![Fire, Grass, Ghost, Normal, Water, Ice]);
+        assert_eq!(effectiveness(&ruleset.chart, Fire, Grass), 2.0);
+        assert_eq!(effectiveness(&ruleset.chart, Fire, Fire), 0.5);
+        assert_eq!(effectiveness(&ruleset.chart, Normal, Ghost), 0.0);
+        assert_eq!(effectiveness(&ruleset.chart, Grass, Fire), 1.0);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let unknown = ChartFile::parse("types = [\"fire\", \"sound\"]").unwrap();
+        assert!(unknown.to_ruleset().is_err());
+
+        let stray = ChartFile::parse(
+            r#"
+            types = ["fire", "grass", "ghost", "normal", "water", "ice"]
+
+            [rock]
+            no_effect = []
+            "#,
+        )
+        .unwrap();
+        assert!(stray.to_ruleset().is_err());
+
+        let few =
+            ChartFile::parse(r#"types = ["fire", "grass", "ghost", "normal", "water", "fire"]"#)
+                .unwrap();
+        assert!(few.to_ruleset().is_err());
+
+        let stray = ChartFile::parse(
+            r#"
+            types = ["fire", "grass", "ghost", "normal", "water", "ice"]
+
+            [fire]
+            no_effect = ["rock"]
+            "#,
+        )
+        .unwrap();
+        assert!(stray.to_ruleset().is_err());
+
+        assert!(ChartFile::parse("types = [\"fire\"]\n[fire]\nsuper = []").is_err());
+    }
+
+    #[test]
+    fn test_bundled_chart() {
+        let ruleset = ChartFile::load("charts/gen6.toml")
+            .unwrap()
+            .to_ruleset()
+            .unwrap();
+        let builtin = Ruleset::from_generation(Generation::Gen6);
+
+        assert_eq!(ruleset.types, builtin.types);
+        assert_eq!(ruleset.chart, builtin.chart);
+    }
+}
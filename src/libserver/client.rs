@@ -1,44 +1,68 @@
-use crate::libserver::utils::TYPES;
-use rand::{rngs::ThreadRng, seq::SliceRandom};
-use futures_channel::mpsc::{UnboundedSender};
+use crate::libserver::utils::SESSION_LENGTH;
+use futures_channel::mpsc::UnboundedSender;
+use rand::{distributions::Alphanumeric, rngs::ThreadRng, Rng};
+use std::{net::SocketAddr, time::Instant};
 use tungstenite::protocol::Message;
-use std::net::SocketAddr;
+use typestrainer::{
+    protocol::{encode, Feature, ServerMessage},
+    round::deal,
+    types::{Choice, PokemonType},
+};
 
 pub type Tx = UnboundedSender<Message>;
 
 pub struct Client {
     pub addr: SocketAddr,
     pub tx: Tx,
-    pub choices: Option<Vec<String>>,
-    pub selected: Option<String>,
+    pub choices: Option<Vec<Choice>>,
+    pub selected: Option<Choice>,
     pub ready: bool,
+    pub features: Vec<Feature>,
+    // Lets the client take its slot back after a dropped connection.
+    pub session: String,
+    pub disconnected: Option<Instant>,
 }
 
 impl Client {
-    pub fn new(addr: SocketAddr, tx: Tx) -> Client {
+    pub fn new(addr: SocketAddr, tx: Tx, features: Vec<Feature>) -> Client {
         Client {
             addr,
             tx,
             choices: None,
             selected: None,
             ready: false,
+            features,
+            session: rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(SESSION_LENGTH)
+                .map(char::from)
+                .collect(),
+            disconnected: None,
         }
     }
 
-    pub fn set_choices(&mut self, rng: &mut ThreadRng) {
-        self.choices = Some(
-            TYPES
-                .choose_multiple(rng, 3)
-                .cloned()
-                .map(|x| x.to_string())
-                .collect(),
-        );
+    // Moves the slot to the new connection of a resuming client.
+    pub fn rebind(&mut self, client: Client) {
+        self.addr = client.addr;
+        self.tx = client.tx;
+        self.features = client.features;
+        self.disconnected = None;
+    }
+
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.features.contains(&feature)
     }
 
-    pub fn send_outcome(&self, status: &'static str, yours: String, theirs: String) {
-        let msg = format!("{};{};{}", status, yours, theirs);
-        self.tx
-            .unbounded_send(tungstenite::Message::Text(msg))
-            .unwrap();
+    pub fn set_choices(&mut self, rng: &mut ThreadRng, types: &[PokemonType], dual_odds: f64) {
+        self.choices = Some(deal(rng, types, dual_odds));
     }
-}
\ No newline at end of file
+
+    // Messages to a dropped connection are lost, a resumed client gets a
+    // snapshot instead.
+    pub fn send(&self, message: &ServerMessage) {
+        let message = Message::Text(encode(message));
+        if self.tx.unbounded_send(message).is_err() {
+            println!("{} is disconnected, message dropped", self.addr);
+        }
+    }
+}
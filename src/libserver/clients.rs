@@ -1,14 +1,55 @@
-use crate::{compute_scores, make_strengths_graph, make_weaknesses_graph, Client};
-use std::{cmp::Ordering, net::SocketAddr};
+use crate::libserver::{client::Client, config::OnTimeout};
+use rand::seq::SliceRandom;
+use std::{
+    cmp::Ordering,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use typestrainer::{
+    chart::Ruleset,
+    protocol::{ErrorCode, Feature, Outcome, ProtocolError, ServerMessage},
+    round::{play, DUAL_TYPE_ODDS},
+    score::MatchScore,
+    types::{format_choice, Choice},
+};
+
+fn not_playing(addr: SocketAddr, id: u32) -> ProtocolError {
+    ProtocolError::new(
+        ErrorCode::NotInRoom,
+        format!("{} is not playing in room {}", addr, id),
+    )
+}
 
 pub struct Clients {
+    pub id: u32,
     pub p1: Option<Client>,
     pub p2: Option<Client>,
+    pub ruleset: Arc<Ruleset>,
+    pub score: MatchScore,
+    pub code: Option<String>,
+    pub password: Option<String>,
+    // Read-only connections following the match.
+    pub spectators: Vec<Client>,
+    // Counts the rounds dealt, for timers to tell whether theirs is over.
+    pub deal: u64,
+    pub deadline: Option<Instant>,
 }
 
 impl Clients {
-    pub fn default() -> Clients {
-        Clients { p1: None, p2: None }
+    pub fn new(id: u32, ruleset: Arc<Ruleset>, best_of: u32) -> Clients {
+        Clients {
+            id,
+            p1: None,
+            p2: None,
+            ruleset,
+            score: MatchScore::new(best_of),
+            code: None,
+            password: None,
+            spectators: vec![],
+            deal: 0,
+            deadline: None,
+        }
     }
 
     pub fn add(&mut self, client: Client) {
@@ -21,14 +62,65 @@ impl Clients {
         }
     }
 
-    pub fn set_ready(&mut self, addr: SocketAddr) {
-        if let Some(ref mut p1) = self.p1 {
-            p1.ready = p1.ready || p1.addr == addr;
+    pub fn remove(&mut self, addr: SocketAddr) -> Option<Client> {
+        if self.p1.as_ref().map(|p| p.addr) == Some(addr) {
+            self.p1.take()
+        } else if self.p2.as_ref().map(|p| p.addr) == Some(addr) {
+            self.p2.take()
+        } else {
+            None
         }
+    }
 
-        if let Some(ref mut p2) = self.p2 {
-            p2.ready = p2.ready || p2.addr == addr;
+    pub fn add_spectator(&mut self, client: Client) {
+        self.spectators.push(client);
+    }
+
+    pub fn remove_spectator(&mut self, addr: SocketAddr) -> Option<Client> {
+        let index = self.spectators.iter().position(|s| s.addr == addr)?;
+        Some(self.spectators.remove(index))
+    }
+
+    pub fn is_watched_by(&self, addr: SocketAddr) -> bool {
+        self.spectators.iter().any(|s| s.addr == addr)
+    }
+
+    pub fn contains(&self, addr: SocketAddr) -> bool {
+        self.players().any(|p| p.addr == addr)
+    }
+
+    pub fn players(&self) -> impl Iterator<Item = &Client> {
+        self.p1.iter().chain(self.p2.iter())
+    }
+
+    pub fn players_mut(&mut self) -> impl Iterator<Item = &mut Client> {
+        self.p1.iter_mut().chain(self.p2.iter_mut())
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.p1.is_some() && self.p2.is_some()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.p1.is_none() && self.p2.is_none()
+    }
+
+    // A player ready again before the end of the round would be dealt new
+    // choices.
+    pub fn set_ready(&mut self, addr: SocketAddr) -> Result<(), ProtocolError> {
+        let id = self.id;
+        let p = self
+            .players_mut()
+            .find(|p| p.addr == addr)
+            .ok_or_else(|| not_playing(addr, id))?;
+        if p.ready {
+            return Err(ProtocolError::new(
+                ErrorCode::AlreadyReady,
+                format!("{} is already ready", addr),
+            ));
         }
+        p.ready = true;
+        Ok(())
     }
 
     pub fn both_ready(&self) -> bool {
@@ -42,50 +134,133 @@ impl Clients {
         false
     }
 
-    pub fn send_choices(&mut self) {
+    pub fn player_with_session(&mut self, session: &str) -> Option<&mut Client> {
+        self.players_mut().find(|p| p.session == session)
+    }
+
+    pub fn send_joined(&self, addr: SocketAddr) {
+        if let Some(p) = self.players().find(|p| p.addr == addr) {
+            p.send(&ServerMessage::Joined {
+                room: self.id,
+                types: self.ruleset.types.clone(),
+                session: p.session.clone(),
+            });
+        }
+    }
+
+    pub fn send_snapshot(&self, addr: SocketAddr) {
+        let (player, opponent, score) = match (&self.p1, &self.p2) {
+            (Some(p1), p2) if p1.addr == addr => (p1, p2, self.score),
+            (p1, Some(p2)) if p2.addr == addr => (p2, p1, self.score.swapped()),
+            _ => return,
+        };
+        player.send(&ServerMessage::Snapshot {
+            room: self.id,
+            code: self.code.clone(),
+            types: self.ruleset.types.clone(),
+            score,
+            ready: player.ready,
+            yours: player.choices.clone(),
+            theirs: opponent.as_ref().and_then(|p| p.choices.clone()),
+            selected: player.selected.clone(),
+            time: self
+                .deadline
+                .map(|deadline| deadline.saturating_duration_since(Instant::now()).as_secs()),
+        });
+    }
+
+    // Players who never start the next round are made ready, so that the
+    // match goes on.
+    pub fn force_ready(&mut self) -> bool {
+        for p in self.players_mut() {
+            p.ready = true;
+        }
+        self.both_ready()
+    }
+
+    pub fn send_choices(&mut self, time: Duration) {
         let mut rng = rand::thread_rng();
+        let dual_odds = if self.players().all(|p| p.supports(Feature::DualTypes)) {
+            DUAL_TYPE_ODDS
+        } else {
+            0.0
+        };
 
         let p1_choices = match self.p1 {
             Some(ref mut p) => {
-                p.set_choices(&mut rng);
-                p.choices.as_ref().unwrap().join(",")
+                p.set_choices(&mut rng, &self.ruleset.types, dual_odds);
+                p.choices.clone().unwrap()
             }
             _ => panic!("Unset client 'p1' cannot get choices !"),
         };
 
         let p2_choices = match self.p2 {
             Some(ref mut p) => {
-                p.set_choices(&mut rng);
-                p.choices.as_ref().unwrap().join(",")
+                p.set_choices(&mut rng, &self.ruleset.types, dual_odds);
+                p.choices.clone().unwrap()
             }
             _ => panic!("Unset client 'p2' cannot get choices !"),
         };
 
+        self.deal += 1;
+        self.deadline = Some(Instant::now() + time);
+        let time = time.as_secs();
+
         if let Some(ref p) = self.p1 {
-            let msg = format!("yours:{};theirs:{}", p1_choices, p2_choices);
-            p.tx.unbounded_send(tungstenite::Message::Text(msg))
-                .unwrap();
+            p.send(&ServerMessage::Choices {
+                yours: p1_choices.clone(),
+                theirs: p2_choices.clone(),
+                time,
+            });
         }
 
         if let Some(ref p) = self.p2 {
-            let msg = format!("yours:{};theirs:{}", p2_choices, p1_choices);
-            p.tx.unbounded_send(tungstenite::Message::Text(msg))
-                .unwrap();
+            p.send(&ServerMessage::Choices {
+                yours: p2_choices.clone(),
+                theirs: p1_choices.clone(),
+                time,
+            });
         }
+
+        self.send_spectators(&ServerMessage::Dealt {
+            p1: p1_choices,
+            p2: p2_choices,
+        });
     }
 
-    pub fn set_selected(&mut self, addr: SocketAddr, type_: String) {
-        if let Some(ref mut p) = self.p1 {
-            if p.addr == addr {
-                p.selected = Some(type_.clone());
-            }
+    // Only one of the choices dealt to the player can be selected, once per
+    // round.
+    pub fn set_selected(&mut self, addr: SocketAddr, type_: Choice) -> Result<(), ProtocolError> {
+        let (player, p) = match (&mut self.p1, &mut self.p2) {
+            (Some(p1), _) if p1.addr == addr => (1, p1),
+            (_, Some(p2)) if p2.addr == addr => (2, p2),
+            _ => return Err(not_playing(addr, self.id)),
+        };
+        let choices = p.choices.as_ref().ok_or_else(|| {
+            ProtocolError::new(
+                ErrorCode::ChoicesNotDealt,
+                format!("no choices were dealt to {} yet", addr),
+            )
+        })?;
+        if p.selected.is_some() {
+            return Err(ProtocolError::new(
+                ErrorCode::AlreadySelected,
+                format!("{} already selected this round", addr),
+            ));
         }
-
-        if let Some(ref mut p) = self.p2 {
-            if p.addr == addr {
-                p.selected = Some(type_);
-            }
+        if !choices.contains(&type_) {
+            return Err(ProtocolError::new(
+                ErrorCode::InvalidChoice,
+                format!("{} was not dealt {}", addr, format_choice(&type_)),
+            ));
         }
+
+        p.selected = Some(type_.clone());
+        self.send_spectators(&ServerMessage::Picked {
+            player,
+            choice: type_,
+        });
+        Ok(())
     }
 
     pub fn both_selected(&self) -> bool {
@@ -99,9 +274,9 @@ impl Clients {
         false
     }
 
-    pub fn get_selected(&self) -> Option<(String, String)> {
-        let p1_selected: String;
-        let p2_selected: String;
+    pub fn get_selected(&self) -> Option<(Choice, Choice)> {
+        let p1_selected: Choice;
+        let p2_selected: Choice;
         if let Some(ref p1) = self.p1 {
             if let Some(ref p1s) = p1.selected {
                 p1_selected = p1s.clone();
@@ -125,61 +300,157 @@ impl Clients {
         Some((p1_selected, p2_selected))
     }
 
-    pub fn send_outcomes(&self) {
+    pub fn send_outcomes(&mut self, next_round: Duration) {
         if let Some((p1_selected, p2_selected)) = self.get_selected() {
             let p1 = self.p1.as_ref().unwrap();
             let p2 = self.p2.as_ref().unwrap();
 
-            let strengths = make_strengths_graph();
-            let weaknesses = make_weaknesses_graph();
-
-            let (p1_score, p2_score) = compute_scores(
-                p1_selected.clone(),
-                p2_selected.clone(),
-                strengths,
-                weaknesses,
+            let (result, p1_attack, p2_attack) =
+                play(&self.ruleset.chart, &p1_selected, &p2_selected);
+            println!(
+                "p1: {} vs p2: {}",
+                p1_attack.multiplier(),
+                p2_attack.multiplier()
             );
 
-            println!("p1: {} vs p2: {}", p1_score, p2_score);
+            self.score.record(result);
+            let (score, swapped) = (self.score, self.score.swapped());
 
-            match p1_score.cmp(&p2_score) {
-                Ordering::Equal => {
-                    p1.send_outcome("tie", p1_selected.clone(), p2_selected.clone());
-                    p2.send_outcome("tie", p1_selected, p2_selected);
-                }
-                Ordering::Greater => {
-                    p1.send_outcome("won", p1_selected.clone(), p2_selected.clone());
-                    p2.send_outcome("lost", p2_selected, p1_selected);
-                }
-                Ordering::Less => {
-                    p1.send_outcome("lost", p1_selected.clone(), p2_selected.clone());
-                    p2.send_outcome("won", p2_selected, p1_selected);
-                }
-            }
+            self.send_spectators(&ServerMessage::Round {
+                outcome: Outcome::from(result),
+                p1: p1_attack.clone(),
+                p2: p2_attack.clone(),
+                score,
+            });
+
+            let next_round = next_round.as_secs();
+            p1.send(&ServerMessage::Outcome {
+                outcome: Outcome::from(result),
+                yours: p1_attack.clone(),
+                theirs: p2_attack.clone(),
+                score,
+                next_round,
+            });
+            p2.send(&ServerMessage::Outcome {
+                outcome: Outcome::from(result.reverse()),
+                yours: p2_attack,
+                theirs: p1_attack,
+                score: swapped,
+                next_round,
+            });
+            self.end_round();
         } else {
             panic!("Cannot find outcome !");
         }
     }
 
-    pub fn send_msg(&self, addr: SocketAddr, msg: String) {
-        if let Some(p1) = &self.p1 {
-            if p1.addr == addr {
-                p1.tx
-                    .unbounded_send(tungstenite::Message::Text(msg.clone()))
-                    .unwrap();
+    // Ends a round past its deadline. Players who did not select either get a
+    // random choice, or lose the round.
+    pub fn time_out(&mut self, on_timeout: OnTimeout, next_round: Duration) {
+        let late: Vec<SocketAddr> = self
+            .players()
+            .filter(|p| p.selected.is_none())
+            .map(|p| p.addr)
+            .collect();
+        println!("room {}: {:?} ran out of time", self.id, late);
+
+        if on_timeout == OnTimeout::Pick {
+            let mut rng = rand::thread_rng();
+            for addr in late {
+                let choice = self
+                    .players()
+                    .find(|p| p.addr == addr)
+                    .and_then(|p| p.choices.as_ref())
+                    .and_then(|choices| choices.choose(&mut rng))
+                    .cloned();
+                // Nothing to pick for a player seated after the deal, who
+                // loses the round instead.
+                if let Some(choice) = choice {
+                    self.set_selected(addr, choice).unwrap();
+                }
+            }
+            if self.both_selected() {
+                self.send_outcomes(next_round);
+                return;
             }
         }
 
-        if let Some(p2) = &self.p2 {
-            if p2.addr == addr {
-                p2.tx
-                    .unbounded_send(tungstenite::Message::Text(msg))
-                    .unwrap();
-            }
+        let late = |p: &Option<Client>| !matches!(p, Some(p) if p.selected.is_some());
+        let result = late(&self.p2).cmp(&late(&self.p1));
+        self.score.record(result);
+        let (score, swapped) = (self.score, self.score.swapped());
+        let next_round = next_round.as_secs();
+        self.send_spectators(&ServerMessage::Timeout {
+            outcome: Outcome::from(result),
+            score,
+            next_round,
+        });
+        if let Some(ref p1) = self.p1 {
+            p1.send(&ServerMessage::Timeout {
+                outcome: Outcome::from(result),
+                score,
+                next_round,
+            });
+        }
+        if let Some(ref p2) = self.p2 {
+            p2.send(&ServerMessage::Timeout {
+                outcome: Outcome::from(result.reverse()),
+                score: swapped,
+                next_round,
+            });
+        }
+        self.end_round();
+    }
+
+    // A finished match starts over with a fresh score.
+    fn end_round(&mut self) {
+        if let Some(winner) = self.score.winner() {
+            println!(
+                "match over, {} won {}-{}.",
+                if winner == Ordering::Greater {
+                    "p1"
+                } else {
+                    "p2"
+                },
+                self.score.wins.0.max(self.score.wins.1),
+                self.score.wins.0.min(self.score.wins.1)
+            );
+            self.score = MatchScore::new(self.score.best_of);
+        }
+    }
+
+    pub fn send_msg(&self, addr: SocketAddr, msg: &ServerMessage) {
+        let mut clients = self.players().chain(self.spectators.iter());
+        if let Some(c) = clients.find(|c| c.addr == addr) {
+            c.send(msg);
+        }
+    }
+
+    pub fn send_spectators(&self, msg: &ServerMessage) {
+        for spectator in self.spectators.iter() {
+            spectator.send(msg);
         }
     }
 
+    pub fn send_watching(&self, addr: SocketAddr) {
+        let choices = |p: &Option<Client>| p.as_ref().and_then(|p| p.choices.clone());
+        let pick = |p: &Option<Client>| p.as_ref().and_then(|p| p.selected.clone());
+        self.send_msg(
+            addr,
+            &ServerMessage::Watching {
+                room: self.id,
+                types: self.ruleset.types.clone(),
+                score: self.score,
+                p1: choices(&self.p1),
+                p2: choices(&self.p2),
+                p1_pick: pick(&self.p1),
+                p2_pick: pick(&self.p2),
+            },
+        );
+    }
+
     pub fn reset(&mut self) {
+        self.deadline = None;
         if let Some(ref mut p1) = self.p1 {
             p1.choices = None;
             p1.selected = None;
@@ -192,3 +463,141 @@ impl Clients {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_channel::mpsc::{unbounded, UnboundedReceiver};
+    use tungstenite::Message;
+    use typestrainer::{
+        chart::Generation,
+        protocol::decode,
+        types::{PokemonType, PokemonType::*},
+    };
+
+    #[test]
+    fn test_set_selected() {
+        let mut room = Clients::new(1, Arc::new(Ruleset::from_generation(Generation::Gen6)), 3);
+        let (a, b, c) = (
+            SocketAddr::from(([127, 0, 0, 1], 1)),
+            SocketAddr::from(([127, 0, 0, 1], 2)),
+            SocketAddr::from(([127, 0, 0, 1], 3)),
+        );
+        let (tx, _rx) = unbounded();
+        room.add(Client::new(a, tx.clone(), vec![]));
+        room.add(Client::new(b, tx, vec![]));
+        let code = |result: Result<(), ProtocolError>| result.unwrap_err().code;
+
+        assert_eq!(
+            code(room.set_selected(a, vec![Fire])),
+            ErrorCode::ChoicesNotDealt
+        );
+        room.send_choices(Duration::from_secs(20));
+        let dealt = room.p1.as_ref().unwrap().choices.clone().unwrap();
+        let other = PokemonType::ALL
+            .iter()
+            .map(|t| vec![*t])
+            .find(|choice| !dealt.contains(choice))
+            .unwrap();
+
+        assert_eq!(
+            code(room.set_selected(c, dealt[0].clone())),
+            ErrorCode::NotInRoom
+        );
+        assert_eq!(code(room.set_selected(a, other)), ErrorCode::InvalidChoice);
+        assert_eq!(
+            code(room.set_selected(a, vec![dealt[0][0], dealt[0][0]])),
+            ErrorCode::InvalidChoice
+        );
+        assert_eq!(room.set_selected(a, dealt[1].clone()), Ok(()));
+        assert_eq!(
+            code(room.set_selected(a, dealt[2].clone())),
+            ErrorCode::AlreadySelected
+        );
+        assert_eq!(room.p1.as_ref().unwrap().selected, Some(dealt[1].clone()));
+        assert!(!room.both_selected());
+    }
+
+    #[test]
+    fn test_set_ready() {
+        let mut room = Clients::new(1, Arc::new(Ruleset::from_generation(Generation::Gen6)), 3);
+        let a = SocketAddr::from(([127, 0, 0, 1], 1));
+        let (tx, _rx) = unbounded();
+        room.add(Client::new(a, tx, vec![]));
+
+        assert_eq!(room.set_ready(a), Ok(()));
+        assert_eq!(room.set_ready(a).unwrap_err().code, ErrorCode::AlreadyReady);
+        room.reset();
+        assert_eq!(room.set_ready(a), Ok(()));
+    }
+
+    #[test]
+    fn test_time_out() {
+        let mut room = Clients::new(1, Arc::new(Ruleset::from_generation(Generation::Gen6)), 5);
+        let (a, b) = (
+            SocketAddr::from(([127, 0, 0, 1], 1)),
+            SocketAddr::from(([127, 0, 0, 1], 2)),
+        );
+        let (a_tx, mut a_rx) = unbounded();
+        let (b_tx, mut b_rx) = unbounded();
+        room.add(Client::new(a, a_tx, vec![]));
+        room.add(Client::new(b, b_tx, vec![]));
+        let last = |rx: &mut UnboundedReceiver<Message>| {
+            let mut last = None;
+            while let Ok(Some(msg)) = rx.try_next() {
+                last = Some(decode::<ServerMessage>(&msg.to_string()).unwrap());
+            }
+            last.unwrap()
+        };
+
+        room.send_choices(Duration::from_secs(20));
+        assert_eq!(room.deal, 1);
+        assert!(room.deadline.is_some());
+        let choice = room.p2.as_ref().unwrap().choices.clone().unwrap();
+        room.set_selected(b, choice[0].clone()).unwrap();
+        room.time_out(OnTimeout::Forfeit, Duration::from_secs(30));
+        room.reset();
+        assert_eq!(room.score.wins, (0, 1));
+        assert!(matches!(
+            last(&mut a_rx),
+            ServerMessage::Timeout {
+                outcome: Outcome::Lost,
+                next_round: 30,
+                ..
+            }
+        ));
+        assert!(matches!(
+            last(&mut b_rx),
+            ServerMessage::Timeout {
+                outcome: Outcome::Won,
+                ..
+            }
+        ));
+
+        room.send_choices(Duration::from_secs(20));
+        room.time_out(OnTimeout::Pick, Duration::from_secs(30));
+        assert!(room.both_selected());
+        assert_eq!(room.score.round, 2);
+        assert!(matches!(last(&mut a_rx), ServerMessage::Outcome { .. }));
+
+        // A player seated after the deal has nothing to pick from.
+        room.reset();
+        room.send_choices(Duration::from_secs(20));
+        room.remove(b);
+        let (c_tx, _c_rx) = unbounded();
+        room.add(Client::new(
+            SocketAddr::from(([127, 0, 0, 1], 3)),
+            c_tx,
+            vec![],
+        ));
+        room.time_out(OnTimeout::Pick, Duration::from_secs(30));
+        assert_eq!(room.score.round, 3);
+        assert!(matches!(
+            last(&mut a_rx),
+            ServerMessage::Timeout {
+                outcome: Outcome::Won,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,241 @@
+use std::time::Duration;
+use typestrainer::{
+    chart::Generation,
+    round::{BEST_OF, READY_TIME, ROUND_TIME},
+    strategy::StrategyKind,
+};
+
+// What happens to a player who does not select before the deadline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OnTimeout {
+    // A random choice is selected for them.
+    Pick,
+    // They lose the round.
+    Forfeit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Timers {
+    pub round: Duration,
+    pub ready: Duration,
+    pub on_timeout: OnTimeout,
+}
+
+// Server bots, seated on request or when nobody comes to play.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bots {
+    pub strategy: StrategyKind,
+    // Taken to think before selecting.
+    pub delay: Duration,
+    // Waiting players get a bot after this long, when set.
+    pub after: Option<Duration>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Serve,
+    Validate,
+}
+
+pub struct Config {
+    pub command: Command,
+    pub addr: String,
+    pub generation: Generation,
+    pub chart: Option<String>,
+    pub best_of: u32,
+    pub grace: Duration,
+    pub timers: Timers,
+    pub bots: Bots,
+}
+
+fn parse_seconds(flag: &str, value: Option<String>) -> Result<Duration, String> {
+    let value = value.ok_or(format!("{} expects a number of seconds", flag))?;
+    value
+        .parse()
+        .ok()
+        .filter(|n| *n > 0)
+        .map(Duration::from_secs)
+        .ok_or(format!("invalid duration '{}' for {}", value, flag))
+}
+
+impl Config {
+    pub fn from_args(args: impl Iterator<Item = String>) -> Result<Config, String> {
+        let mut config = Config {
+            command: Command::Serve,
+            addr: "127.0.0.1:8080".to_string(),
+            generation: Generation::Gen6,
+            chart: None,
+            best_of: BEST_OF,
+            grace: Duration::from_secs(30),
+            timers: Timers {
+                round: ROUND_TIME,
+                ready: READY_TIME,
+                on_timeout: OnTimeout::Pick,
+            },
+            bots: Bots {
+                strategy: StrategyKind::Greedy,
+                delay: Duration::from_secs(1),
+                after: None,
+            },
+        };
+        let mut args = args.peekable();
+
+        if args.peek().map(|arg| &**arg) == Some("validate") {
+            args.next();
+            config.command = Command::Validate;
+        }
+
+        while let Some(arg) = args.next() {
+            match &*arg {
+                "--generation" => {
+                    let value = args.next().ok_or("--generation expects a number")?;
+                    config.generation = value
+                        .parse()
+                        .ok()
+                        .and_then(Generation::from_number)
+                        .ok_or(format!("unknown generation '{}'", value))?;
+                }
+                "--chart" => {
+                    config.chart = Some(args.next().ok_or("--chart expects a path")?);
+                }
+                "--best-of" => {
+                    let value = args.next().ok_or("--best-of expects a number")?;
+                    config.best_of = value
+                        .parse()
+                        .ok()
+                        .filter(|n| *n > 0)
+                        .ok_or(format!("invalid number of rounds '{}'", value))?;
+                }
+                "--grace" => {
+                    let value = args.next().ok_or("--grace expects a number of seconds")?;
+                    config.grace = value
+                        .parse()
+                        .map(Duration::from_secs)
+                        .map_err(|_| format!("invalid grace period '{}'", value))?;
+                }
+                "--round-time" => config.timers.round = parse_seconds(&arg, args.next())?,
+                "--ready-time" => config.timers.ready = parse_seconds(&arg, args.next())?,
+                "--on-timeout" => {
+                    config.timers.on_timeout = match args.next().as_deref() {
+                        Some("pick") => OnTimeout::Pick,
+                        Some("forfeit") => OnTimeout::Forfeit,
+                        _ => return Err("--on-timeout expects 'pick' or 'forfeit'".to_string()),
+                    }
+                }
+                "--bot" => {
+                    let value = args.next().ok_or("--bot expects a strategy")?;
+                    config.bots.strategy = StrategyKind::from_name(&value).ok_or(format!(
+                        "unknown strategy '{}', expected {}",
+                        value,
+                        StrategyKind::NAMES
+                    ))?;
+                }
+                "--bot-delay" => {
+                    let value = args.next().ok_or("--bot-delay expects milliseconds")?;
+                    config.bots.delay = value
+                        .parse()
+                        .map(Duration::from_millis)
+                        .map_err(|_| format!("invalid bot delay '{}'", value))?;
+                }
+                "--bot-after" => config.bots.after = Some(parse_seconds(&arg, args.next())?),
+                _ if config.command == Command::Validate => config.chart = Some(arg),
+                _ => config.addr = arg,
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> impl Iterator<Item = String> + '_ {
+        line.split_whitespace().map(String::from)
+    }
+
+    #[test]
+    fn test_defaults() {
+        let config = Config::from_args(args("")).unwrap();
+        assert_eq!(config.command, Command::Serve);
+        assert_eq!(config.addr, "127.0.0.1:8080");
+        assert_eq!(config.generation, Generation::Gen6);
+        assert_eq!(config.chart, None);
+        assert_eq!(config.best_of, 3);
+        assert_eq!(config.grace, Duration::from_secs(30));
+        assert_eq!(config.timers.round, Duration::from_secs(20));
+        assert_eq!(config.timers.ready, Duration::from_secs(30));
+        assert_eq!(config.timers.on_timeout, OnTimeout::Pick);
+        assert_eq!(config.bots.strategy, StrategyKind::Greedy);
+        assert_eq!(config.bots.delay, Duration::from_secs(1));
+        assert_eq!(config.bots.after, None);
+    }
+
+    #[test]
+    fn test_generation() {
+        let config = Config::from_args(args("0.0.0.0:9000 --generation 3")).unwrap();
+        assert_eq!(config.addr, "0.0.0.0:9000");
+        assert_eq!(config.generation, Generation::Gen2To5);
+
+        assert!(Config::from_args(args("--generation 12")).is_err());
+        assert!(Config::from_args(args("--generation")).is_err());
+    }
+
+    #[test]
+    fn test_chart() {
+        let config = Config::from_args(args("--chart charts/gen6.toml")).unwrap();
+        assert_eq!(config.chart, Some("charts/gen6.toml".to_string()));
+
+        assert!(Config::from_args(args("--chart")).is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let config = Config::from_args(args("validate charts/gen6.toml --generation 5")).unwrap();
+        assert_eq!(config.command, Command::Validate);
+        assert_eq!(config.chart, Some("charts/gen6.toml".to_string()));
+        assert_eq!(config.generation, Generation::Gen2To5);
+    }
+
+    #[test]
+    fn test_best_of() {
+        let config = Config::from_args(args("--best-of 5")).unwrap();
+        assert_eq!(config.best_of, 5);
+
+        assert!(Config::from_args(args("--best-of 0")).is_err());
+    }
+
+    #[test]
+    fn test_grace() {
+        let config = Config::from_args(args("--grace 10")).unwrap();
+        assert_eq!(config.grace, Duration::from_secs(10));
+
+        assert!(Config::from_args(args("--grace soon")).is_err());
+    }
+
+    #[test]
+    fn test_timers() {
+        let config =
+            Config::from_args(args("--round-time 10 --ready-time 5 --on-timeout forfeit")).unwrap();
+        assert_eq!(config.timers.round, Duration::from_secs(10));
+        assert_eq!(config.timers.ready, Duration::from_secs(5));
+        assert_eq!(config.timers.on_timeout, OnTimeout::Forfeit);
+
+        assert!(Config::from_args(args("--round-time 0")).is_err());
+        assert!(Config::from_args(args("--ready-time")).is_err());
+        assert!(Config::from_args(args("--on-timeout wait")).is_err());
+    }
+
+    #[test]
+    fn test_bots() {
+        let config = Config::from_args(args("--bot nash --bot-delay 250 --bot-after 15")).unwrap();
+        assert_eq!(config.bots.strategy, StrategyKind::Nash);
+        assert_eq!(config.bots.delay, Duration::from_millis(250));
+        assert_eq!(config.bots.after, Some(Duration::from_secs(15)));
+
+        assert!(Config::from_args(args("--bot psychic")).is_err());
+        assert!(Config::from_args(args("--bot-delay soon")).is_err());
+        assert!(Config::from_args(args("--bot-after 0")).is_err());
+    }
+}
@@ -0,0 +1,77 @@
+use std::time::{Duration, Instant};
+use typestrainer::protocol::ServerMessage;
+
+// Round trip tracking of a connection, pinged every `HEARTBEAT_INTERVAL`.
+pub struct Heartbeat {
+    next_id: u64,
+    sent: Option<(u64, Instant)>,
+    last_seen: Instant,
+    pub rtt: Option<Duration>,
+}
+
+impl Heartbeat {
+    pub fn new() -> Heartbeat {
+        Heartbeat {
+            next_id: 1,
+            sent: None,
+            last_seen: Instant::now(),
+            rtt: None,
+        }
+    }
+
+    // Any message shows the connection is alive.
+    pub fn seen(&mut self) {
+        self.last_seen = Instant::now();
+    }
+
+    pub fn is_dead(&self, timeout: Duration) -> bool {
+        self.last_seen.elapsed() > timeout
+    }
+
+    // Only the latest ping is answered, an earlier pong arriving late is
+    // ignored.
+    pub fn ping(&mut self) -> ServerMessage {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.sent = Some((id, Instant::now()));
+        ServerMessage::Ping {
+            id,
+            rtt: self.rtt.map(|rtt| rtt.as_millis() as u64),
+        }
+    }
+
+    pub fn pong(&mut self, id: u64) {
+        if let Some((sent, at)) = self.sent {
+            if sent == id {
+                self.rtt = Some(at.elapsed());
+                self.sent = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heartbeat() {
+        let mut heartbeat = Heartbeat::new();
+        assert!(!heartbeat.is_dead(Duration::from_secs(1)));
+
+        assert_eq!(heartbeat.ping(), ServerMessage::Ping { id: 1, rtt: None });
+        assert_eq!(heartbeat.ping(), ServerMessage::Ping { id: 2, rtt: None });
+        heartbeat.pong(1);
+        assert_eq!(heartbeat.rtt, None);
+        heartbeat.pong(2);
+        let rtt = heartbeat.rtt.unwrap();
+        assert!(rtt < Duration::from_secs(1));
+        assert_eq!(
+            heartbeat.ping(),
+            ServerMessage::Ping {
+                id: 3,
+                rtt: Some(rtt.as_millis() as u64)
+            }
+        );
+    }
+}
@@ -0,0 +1,1105 @@
+use crate::libserver::{
+    bot::is_bot,
+    client::Client,
+    clients::Clients,
+    utils::{JOIN_CODE_CHARS, JOIN_CODE_LENGTH},
+};
+use rand::{seq::SliceRandom, Rng};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use typestrainer::{
+    chart::{Generation, Ruleset},
+    protocol::{ErrorCode, Feature, ProtocolError, RoomInfo, ServerMessage},
+    score::MatchScore,
+};
+
+fn already_in_room(addr: SocketAddr) -> ProtocolError {
+    ProtocolError::new(
+        ErrorCode::AlreadyInRoom,
+        format!("{} is already in a room", addr),
+    )
+}
+
+fn room_not_found(id: u32) -> ProtocolError {
+    ProtocolError::new(ErrorCode::RoomNotFound, format!("no room {}", id))
+}
+
+fn no_rulesets(addr: SocketAddr) -> ProtocolError {
+    ProtocolError::new(
+        ErrorCode::UnsupportedFeature,
+        format!("{} does not support rulesets", addr),
+    )
+}
+
+// A match request, with the generation the client wants to play if any
+// and whether it asks for an Inverse Battle.
+pub struct Request {
+    pub addr: SocketAddr,
+    pub generation: Option<Generation>,
+    pub inverse: bool,
+}
+
+pub struct Lobby {
+    pub rooms: BTreeMap<u32, Clients>,
+    pub idle: BTreeMap<SocketAddr, Client>,
+    pub queue: VecDeque<Request>,
+    // Rooms opened by the queue, with the generation asked for.
+    matched: BTreeMap<u32, Option<Generation>>,
+    next_id: u32,
+    ruleset: Arc<Ruleset>,
+    best_of: u32,
+}
+
+impl Lobby {
+    pub fn new(ruleset: Arc<Ruleset>, best_of: u32) -> Lobby {
+        Lobby {
+            rooms: BTreeMap::new(),
+            idle: BTreeMap::new(),
+            queue: VecDeque::new(),
+            matched: BTreeMap::new(),
+            next_id: 1,
+            ruleset,
+            best_of,
+        }
+    }
+
+    pub fn connect(&mut self, client: Client) {
+        let addr = client.addr;
+        self.idle.insert(addr, client);
+        self.send_rooms(addr);
+    }
+
+    pub fn room_of(&self, addr: SocketAddr) -> Option<u32> {
+        self.rooms
+            .values()
+            .find(|room| room.contains(addr))
+            .map(|room| room.id)
+    }
+
+    pub fn room_mut(&mut self, addr: SocketAddr) -> Option<&mut Clients> {
+        self.rooms.values_mut().find(|room| room.contains(addr))
+    }
+
+    pub fn watched_room(&self, addr: SocketAddr) -> Option<u32> {
+        self.rooms
+            .values()
+            .find(|room| room.is_watched_by(addr))
+            .map(|room| room.id)
+    }
+
+    // The creator picks the ruleset of the room, Inverse Battle or not.
+    pub fn create(&mut self, addr: SocketAddr, inverse: bool) -> Result<u32, ProtocolError> {
+        self.check_create(addr, inverse)?;
+        let id = self.open_room(self.ruleset_for(None, inverse));
+        self.add_player(addr, id)?;
+        Ok(id)
+    }
+
+    // Private rooms are left out of the room list and can only be entered
+    // with their join code, and password when one was set.
+    pub fn create_private(
+        &mut self,
+        addr: SocketAddr,
+        password: Option<String>,
+        inverse: bool,
+    ) -> Result<String, ProtocolError> {
+        self.check_create(addr, inverse)?;
+        let code = self.new_code(&mut rand::thread_rng());
+        let id = self.open_room(self.ruleset_for(None, inverse));
+        let room = self.rooms.get_mut(&id).unwrap();
+        room.code = Some(code.clone());
+        room.password = password;
+
+        self.send_msg(addr, &ServerMessage::Code { code: code.clone() });
+        self.add_player(addr, id)?;
+        Ok(code)
+    }
+
+    fn check_create(&self, addr: SocketAddr, inverse: bool) -> Result<(), ProtocolError> {
+        match self.idle.get(&addr) {
+            None => Err(already_in_room(addr)),
+            Some(client) if inverse && !client.supports(Feature::Rulesets) => {
+                Err(no_rulesets(addr))
+            }
+            Some(_) => Ok(()),
+        }
+    }
+
+    fn new_code(&self, rng: &mut impl Rng) -> String {
+        loop {
+            let code: String = (0..JOIN_CODE_LENGTH)
+                .map(|_| *JOIN_CODE_CHARS.choose(rng).unwrap() as char)
+                .collect();
+            if self.room_with_code(&code).is_none() {
+                return code;
+            }
+        }
+    }
+
+    fn room_with_code(&self, code: &str) -> Option<u32> {
+        self.rooms
+            .values()
+            .find(|room| room.code.as_deref() == Some(code))
+            .map(|room| room.id)
+    }
+
+    fn open_room(&mut self, ruleset: Arc<Ruleset>) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.rooms
+            .insert(id, Clients::new(id, ruleset, self.best_of));
+        id
+    }
+
+    fn ruleset_for(&self, generation: Option<Generation>, inverse: bool) -> Arc<Ruleset> {
+        match (generation, inverse) {
+            (Some(generation), true) => Arc::new(Ruleset::from_generation(generation).inverted()),
+            (Some(generation), false) => Arc::new(Ruleset::from_generation(generation)),
+            (None, true) => Arc::new(self.ruleset.inverted()),
+            (None, false) => self.ruleset.clone(),
+        }
+    }
+
+    pub fn join(&mut self, addr: SocketAddr, id: u32) -> Result<(), ProtocolError> {
+        match self.rooms.get(&id) {
+            Some(room) if room.code.is_some() => Err(ProtocolError::new(
+                ErrorCode::RoomPrivate,
+                format!("room {} is private", id),
+            )),
+            Some(_) => self.add_player(addr, id),
+            None => Err(room_not_found(id)),
+        }
+    }
+
+    pub fn enter(
+        &mut self,
+        addr: SocketAddr,
+        code: &str,
+        password: Option<&str>,
+    ) -> Result<u32, ProtocolError> {
+        let code = code.to_uppercase();
+        let id = self.room_with_code(&code).ok_or_else(|| {
+            ProtocolError::new(
+                ErrorCode::RoomNotFound,
+                format!("no room with code {}", code),
+            )
+        })?;
+        match self.rooms[&id].password {
+            Some(ref expected) if Some(&**expected) != password => Err(ProtocolError::new(
+                ErrorCode::WrongPassword,
+                format!("wrong password for room {}", id),
+            )),
+            _ => self.add_player(addr, id).map(|_| id),
+        }
+    }
+
+    // Spectators follow a public room without playing, any number of them.
+    pub fn watch(&mut self, addr: SocketAddr, id: u32) -> Result<(), ProtocolError> {
+        let room = self.rooms.get_mut(&id).ok_or_else(|| room_not_found(id))?;
+        if room.code.is_some() {
+            return Err(ProtocolError::new(
+                ErrorCode::RoomPrivate,
+                format!("room {} is private", id),
+            ));
+        }
+        let client = self
+            .idle
+            .remove(&addr)
+            .ok_or_else(|| already_in_room(addr))?;
+
+        room.add_spectator(client);
+        room.send_watching(addr);
+        if self.dequeue(addr) {
+            self.send_positions();
+        }
+        self.broadcast_rooms();
+        Ok(())
+    }
+
+    pub fn unwatch(&mut self, addr: SocketAddr) -> Result<(), ProtocolError> {
+        let id = self.watched_room(addr).ok_or_else(|| {
+            ProtocolError::new(
+                ErrorCode::NotSpectating,
+                format!("{} is not watching a room", addr),
+            )
+        })?;
+        let client = self.rooms.get_mut(&id).unwrap().remove_spectator(addr);
+        self.broadcast_rooms();
+        self.connect(client.unwrap());
+        Ok(())
+    }
+
+    fn add_player(&mut self, addr: SocketAddr, id: u32) -> Result<(), ProtocolError> {
+        let room = self.rooms.get_mut(&id).ok_or_else(|| room_not_found(id))?;
+        if room.is_full() {
+            return Err(ProtocolError::new(
+                ErrorCode::RoomFull,
+                format!("room {} is full", id),
+            ));
+        }
+        let client = self
+            .idle
+            .remove(&addr)
+            .ok_or_else(|| already_in_room(addr))?;
+
+        room.add(client);
+        room.send_joined(addr);
+        if self.dequeue(addr) {
+            self.send_positions();
+        }
+        self.broadcast_rooms();
+        Ok(())
+    }
+
+    // Seats a bot against a client: in its room when a seat is free, right
+    // away when it waits for a match, or else in a new room.
+    pub fn seat_bot(&mut self, addr: SocketAddr, bot: Client) -> Result<u32, ProtocolError> {
+        let id = match self.room_of(addr) {
+            Some(id) => id,
+            None => match self.queue.iter().find(|queued| queued.addr == addr) {
+                Some(request) => {
+                    let generation = request.generation;
+                    let id = self.open_room(self.ruleset_for(generation, request.inverse));
+                    self.matched.insert(id, generation);
+                    self.add_player(addr, id)?;
+                    id
+                }
+                None => self.create(addr, false)?,
+            },
+        };
+        let bot_addr = bot.addr;
+        self.idle.insert(bot_addr, bot);
+        if let Err(e) = self.add_player(bot_addr, id) {
+            self.idle.remove(&bot_addr);
+            return Err(e);
+        }
+        Ok(id)
+    }
+
+    // Waiting clients stay in the lobby and are paired first come, first
+    // served with the oldest request playing the same generation, and
+    // Inverse Battles are only played against someone who asked for one.
+    pub fn enqueue(&mut self, request: Request) -> Result<(), ProtocolError> {
+        let addr = request.addr;
+        let client = self.idle.get(&addr).ok_or_else(|| already_in_room(addr))?;
+        if (request.generation.is_some() || request.inverse) && !client.supports(Feature::Rulesets)
+        {
+            return Err(no_rulesets(addr));
+        }
+        if self.queue.iter().any(|queued| queued.addr == addr) {
+            return Err(ProtocolError::new(
+                ErrorCode::AlreadyQueued,
+                format!("{} is already waiting for a match", addr),
+            ));
+        }
+
+        self.queue.push_back(request);
+        while let Some((i, j)) = self.find_pair() {
+            let (p2, p1) = (self.queue.remove(j).unwrap(), self.queue.remove(i).unwrap());
+            let generation = p1.generation.or(p2.generation);
+            let id = self.open_room(self.ruleset_for(generation, p1.inverse));
+            self.matched.insert(id, generation);
+            self.add_player(p1.addr, id)?;
+            self.add_player(p2.addr, id)?;
+        }
+        self.send_positions();
+        Ok(())
+    }
+
+    fn find_pair(&self) -> Option<(usize, usize)> {
+        (0..self.queue.len()).find_map(|i| {
+            (i + 1..self.queue.len())
+                .find(|j| self.accepts(&self.queue[i], &self.queue[*j]))
+                .map(|j| (i, j))
+        })
+    }
+
+    // Clients without rulesets support only play with the default ruleset.
+    fn accepts(&self, a: &Request, b: &Request) -> bool {
+        let rulesets = |r: &Request| self.idle[&r.addr].supports(Feature::Rulesets);
+        match (a.generation, b.generation) {
+            _ if a.inverse != b.inverse => false,
+            (None, None) => true,
+            (Some(x), Some(y)) if x != y => false,
+            _ => rulesets(a) && rulesets(b),
+        }
+    }
+
+    pub fn cancel(&mut self, addr: SocketAddr) -> Result<(), ProtocolError> {
+        if !self.dequeue(addr) {
+            return Err(ProtocolError::new(
+                ErrorCode::NotQueued,
+                format!("{} is not waiting for a match", addr),
+            ));
+        }
+        self.send_positions();
+        self.send_rooms(addr);
+        Ok(())
+    }
+
+    fn dequeue(&mut self, addr: SocketAddr) -> bool {
+        let before = self.queue.len();
+        self.queue.retain(|queued| queued.addr != addr);
+        self.queue.len() != before
+    }
+
+    pub fn send_positions(&self) {
+        for (position, request) in self.queue.iter().enumerate() {
+            let position = position + 1;
+            self.send_msg(request.addr, &ServerMessage::Queued { position });
+        }
+    }
+
+    // Players keep their slot when their connection drops, until they resume
+    // their session or it expires. Returns the session and the time of the
+    // disconnection to expire it later.
+    pub fn disconnect(&mut self, addr: SocketAddr, grace: Duration) -> Option<(String, Instant)> {
+        let room = match self.room_mut(addr) {
+            Some(room) => room,
+            None => {
+                self.leave(addr);
+                return None;
+            }
+        };
+        let player = room.players_mut().find(|p| p.addr == addr).unwrap();
+        let at = Instant::now();
+        player.disconnected = Some(at);
+        let session = player.session.clone();
+        for opponent in room.players().filter(|p| p.addr != addr) {
+            opponent.send(&ServerMessage::OpponentDisconnected {
+                grace: grace.as_secs(),
+            });
+        }
+        Some((session, at))
+    }
+
+    pub fn resume(&mut self, addr: SocketAddr, session: &str) -> Result<u32, ProtocolError> {
+        if !self.idle.contains_key(&addr) {
+            return Err(already_in_room(addr));
+        }
+        let room = self
+            .rooms
+            .values_mut()
+            .find(|room| {
+                room.players()
+                    .any(|p| p.session == session && p.disconnected.is_some())
+            })
+            .ok_or_else(|| {
+                ProtocolError::new(
+                    ErrorCode::SessionNotFound,
+                    format!("no session {} to resume", session),
+                )
+            })?;
+
+        let client = self.idle.remove(&addr).unwrap();
+        room.player_with_session(session).unwrap().rebind(client);
+        room.send_snapshot(addr);
+        for opponent in room.players().filter(|p| p.addr != addr) {
+            opponent.send(&ServerMessage::OpponentReconnected);
+        }
+        let id = room.id;
+        if self.dequeue(addr) {
+            self.send_positions();
+        }
+        Ok(id)
+    }
+
+    // Gives the slot away when the session was not resumed since `at`.
+    pub fn expire(&mut self, session: &str, at: Instant) {
+        let expired = self.rooms.values_mut().find_map(|room| {
+            room.player_with_session(session)
+                .filter(|p| p.disconnected == Some(at))
+                .map(|p| p.addr)
+        });
+        if let Some(addr) = expired {
+            println!("session of {} expired", addr);
+            self.forfeit(addr);
+        }
+    }
+
+    // Closes the room of a player who did not come back. The opponent wins
+    // the match and goes back to the queue when the room was matched by it,
+    // or to the lobby.
+    fn forfeit(&mut self, addr: SocketAddr) {
+        let id = match self.room_of(addr) {
+            Some(id) => id,
+            None => return,
+        };
+        let mut room = self.rooms.remove(&id).unwrap();
+        let generation = self.matched.remove(&id);
+        let score = if room.p1.as_ref().map(|p| p.addr) == Some(addr) {
+            room.score.swapped()
+        } else {
+            room.score
+        };
+        room.remove(addr);
+        room.send_spectators(&ServerMessage::Left);
+
+        let winner = room.p1.take().or_else(|| room.p2.take());
+        self.broadcast_rooms();
+        for spectator in room.spectators {
+            self.connect(spectator);
+        }
+
+        // Nobody to award when both players are gone.
+        let mut winner = match winner {
+            Some(winner) if winner.disconnected.is_none() => winner,
+            _ => return,
+        };
+        println!("{} wins room {} by forfeit", winner.addr, id);
+        winner.send(&ServerMessage::Forfeit { score });
+        // Bots only play against the player they were seated for.
+        if is_bot(winner.addr) {
+            return;
+        }
+        winner.choices = None;
+        winner.selected = None;
+        winner.ready = false;
+        let winner_addr = winner.addr;
+        match generation {
+            Some(generation) => {
+                self.idle.insert(winner_addr, winner);
+                let request = Request {
+                    addr: winner_addr,
+                    generation,
+                    inverse: room.ruleset.inverse,
+                };
+                if let Err(e) = self.enqueue(request) {
+                    self.refuse(winner_addr, e);
+                }
+            }
+            None => self.connect(winner),
+        }
+    }
+
+    // Drops the client from the lobby or its room. The opponent left behind
+    // keeps the room open for someone else, with a fresh match.
+    pub fn leave(&mut self, addr: SocketAddr) {
+        if self.idle.remove(&addr).is_some() {
+            if self.dequeue(addr) {
+                self.send_positions();
+            }
+            return;
+        }
+
+        if let Some(id) = self.watched_room(addr) {
+            self.rooms.get_mut(&id).unwrap().remove_spectator(addr);
+            self.broadcast_rooms();
+        } else if let Some(id) = self.room_of(addr) {
+            let room = self.rooms.get_mut(&id).unwrap();
+            room.remove(addr);
+            room.send_spectators(&ServerMessage::Left);
+            let mut orphans = vec![];
+            if room.is_empty() {
+                orphans = self.rooms.remove(&id).unwrap().spectators;
+                self.matched.remove(&id);
+            } else {
+                // Timers of the old match must not run on the next one.
+                room.deal += 1;
+                room.reset();
+                room.score = MatchScore::new(room.score.best_of);
+                for player in room.players() {
+                    room.send_msg(player.addr, &ServerMessage::Left);
+                }
+            }
+            self.broadcast_rooms();
+            // Spectators of a closed room go back to the lobby.
+            for spectator in orphans {
+                self.connect(spectator);
+            }
+        }
+    }
+
+    pub fn room_list(&self) -> Vec<RoomInfo> {
+        self.rooms
+            .values()
+            .filter(|room| room.code.is_none())
+            .map(|room| RoomInfo {
+                id: room.id,
+                players: room.players().count(),
+                spectators: room.spectators.len(),
+                inverse: room.ruleset.inverse,
+            })
+            .collect()
+    }
+
+    pub fn send_error(&self, addr: SocketAddr, error: ProtocolError) {
+        println!("{}", error);
+        self.send_msg(addr, &ServerMessage::Error(error));
+    }
+
+    // Lobby requests that failed are answered with the room list, so the
+    // client can go back to it.
+    pub fn refuse(&self, addr: SocketAddr, error: ProtocolError) {
+        self.send_error(addr, error);
+        self.send_rooms(addr);
+    }
+
+    pub fn send_rooms(&self, addr: SocketAddr) {
+        let rooms = self.room_list();
+        self.send_msg(addr, &ServerMessage::Rooms { rooms });
+    }
+
+    pub fn broadcast_rooms(&self) {
+        let browsing = self
+            .idle
+            .keys()
+            .filter(|addr| self.queue.iter().all(|queued| queued.addr != **addr));
+        for addr in browsing {
+            self.send_rooms(*addr);
+        }
+    }
+
+    pub fn send_msg(&self, addr: SocketAddr, msg: &ServerMessage) {
+        match self.idle.get(&addr) {
+            Some(client) => client.send(msg),
+            None => {
+                if let Some(id) = self.room_of(addr).or_else(|| self.watched_room(addr)) {
+                    self.rooms[&id].send_msg(addr, msg);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_channel::mpsc::{unbounded, UnboundedReceiver};
+    use std::cmp::Ordering;
+    use tungstenite::Message;
+    use typestrainer::{chart::Generation, protocol::decode};
+
+    fn lobby() -> Lobby {
+        let ruleset = Arc::new(Ruleset::from_generation(Generation::Gen6));
+        Lobby::new(ruleset, 3)
+    }
+
+    fn connect_with(
+        lobby: &mut Lobby,
+        port: u16,
+        features: &[Feature],
+    ) -> (SocketAddr, UnboundedReceiver<Message>) {
+        let addr = SocketAddr::from(([127, 0, 0, 1], port));
+        let (tx, rx) = unbounded();
+        lobby.connect(Client::new(addr, tx, features.to_vec()));
+        (addr, rx)
+    }
+
+    fn connect(lobby: &mut Lobby, port: u16) -> (SocketAddr, UnboundedReceiver<Message>) {
+        connect_with(lobby, port, &Feature::ALL)
+    }
+
+    fn next_message(rx: &mut UnboundedReceiver<Message>) -> ServerMessage {
+        decode(&rx.try_next().unwrap().unwrap().to_string()).unwrap()
+    }
+
+    fn last_message(rx: &mut UnboundedReceiver<Message>) -> ServerMessage {
+        let mut last = None;
+        while let Ok(Some(msg)) = rx.try_next() {
+            last = Some(decode(&msg.to_string()).unwrap());
+        }
+        last.unwrap()
+    }
+
+    fn rooms(rooms: &[(u32, usize)]) -> ServerMessage {
+        ServerMessage::Rooms {
+            rooms: rooms
+                .iter()
+                .map(|(id, players)| RoomInfo {
+                    id: *id,
+                    players: *players,
+                    spectators: 0,
+                    inverse: false,
+                })
+                .collect(),
+        }
+    }
+
+    fn joined(message: ServerMessage) -> Option<(u32, usize)> {
+        match message {
+            ServerMessage::Joined { room, types, .. } => Some((room, types.len())),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_rooms() {
+        let mut lobby = lobby();
+        let (a, mut a_rx) = connect(&mut lobby, 1);
+        let (b, mut b_rx) = connect(&mut lobby, 2);
+        let (c, mut c_rx) = connect(&mut lobby, 3);
+        assert_eq!(last_message(&mut a_rx), rooms(&[]));
+
+        assert_eq!(lobby.create(a, false), Ok(1));
+        assert_eq!(joined(last_message(&mut a_rx)), Some((1, 18)));
+        assert_eq!(last_message(&mut b_rx), rooms(&[(1, 1)]));
+        assert_eq!(
+            lobby.create(a, false).unwrap_err().code,
+            ErrorCode::AlreadyInRoom
+        );
+
+        assert_eq!(lobby.join(b, 1), Ok(()));
+        assert_eq!(last_message(&mut c_rx), rooms(&[(1, 2)]));
+        assert_eq!(lobby.join(c, 1).unwrap_err().code, ErrorCode::RoomFull);
+        assert_eq!(lobby.join(c, 2).unwrap_err().code, ErrorCode::RoomNotFound);
+        assert_eq!(lobby.room_of(b), Some(1));
+        assert_eq!(lobby.room_of(c), None);
+
+        lobby.leave(a);
+        assert_eq!(last_message(&mut b_rx), ServerMessage::Left);
+        assert_eq!(last_message(&mut c_rx), rooms(&[(1, 1)]));
+        assert_eq!(lobby.rooms[&1].deal, 1);
+
+        lobby.leave(b);
+        assert_eq!(last_message(&mut c_rx), rooms(&[]));
+        assert!(lobby.rooms.is_empty());
+
+        assert_eq!(lobby.create(c, true), Ok(2));
+        assert!(lobby.rooms[&2].ruleset.inverse);
+    }
+
+    #[test]
+    fn test_queue() {
+        let mut lobby = lobby();
+        let request = |port: u16, generation: Option<u8>| Request {
+            addr: SocketAddr::from(([127, 0, 0, 1], port)),
+            generation: generation.and_then(Generation::from_number),
+            inverse: false,
+        };
+        let queued = |position| ServerMessage::Queued { position };
+        let mut rxs: Vec<_> = (1..=5).map(|port| connect(&mut lobby, port).1).collect();
+
+        assert_eq!(lobby.enqueue(request(1, Some(1))), Ok(()));
+        assert_eq!(last_message(&mut rxs[0]), queued(1));
+        assert_eq!(
+            lobby.enqueue(request(1, None)).unwrap_err().code,
+            ErrorCode::AlreadyQueued
+        );
+        assert_eq!(lobby.enqueue(request(2, Some(6))), Ok(()));
+        assert_eq!(last_message(&mut rxs[1]), queued(2));
+        assert_eq!(lobby.enqueue(request(3, Some(6))), Ok(()));
+        assert_eq!(last_message(&mut rxs[0]), queued(1));
+        assert_eq!(joined(last_message(&mut rxs[1])), Some((1, 18)));
+        assert_eq!(joined(last_message(&mut rxs[2])), Some((1, 18)));
+
+        // Any generation goes with the oldest request.
+        assert_eq!(lobby.enqueue(request(4, Some(2))), Ok(()));
+        assert_eq!(last_message(&mut rxs[3]), queued(2));
+        assert_eq!(lobby.enqueue(request(5, None)), Ok(()));
+        assert_eq!(joined(last_message(&mut rxs[4])), Some((2, 15)));
+        assert_eq!(last_message(&mut rxs[3]), queued(1));
+        assert_eq!(
+            lobby.enqueue(request(1, None)).unwrap_err().code,
+            ErrorCode::AlreadyInRoom
+        );
+
+        let addr = request(4, None).addr;
+        assert_eq!(lobby.cancel(addr), Ok(()));
+        assert_eq!(lobby.cancel(addr).unwrap_err().code, ErrorCode::NotQueued);
+        assert!(lobby.queue.is_empty());
+        assert_eq!(last_message(&mut rxs[3]), rooms(&[(1, 2), (2, 2)]));
+    }
+
+    #[test]
+    fn test_inverse() {
+        let mut lobby = lobby();
+        let (a, _a_rx) = connect_with(&mut lobby, 1, &[]);
+        let (b, _b_rx) = connect(&mut lobby, 2);
+        let (c, _c_rx) = connect(&mut lobby, 3);
+        let (d, _d_rx) = connect(&mut lobby, 4);
+        let request = |addr, inverse| Request {
+            addr,
+            generation: None,
+            inverse,
+        };
+
+        assert_eq!(
+            lobby.enqueue(request(a, true)).unwrap_err().code,
+            ErrorCode::UnsupportedFeature
+        );
+        assert_eq!(
+            lobby.create(a, true).unwrap_err().code,
+            ErrorCode::UnsupportedFeature
+        );
+        lobby.enqueue(request(b, true)).unwrap();
+        lobby.enqueue(request(a, false)).unwrap();
+        assert_eq!(lobby.queue.len(), 2);
+        lobby.enqueue(request(c, true)).unwrap();
+        let id = lobby.room_of(b).unwrap();
+        assert_eq!(lobby.room_of(c), Some(id));
+        assert!(lobby.rooms[&id].ruleset.inverse);
+        assert_eq!(lobby.queue[0].addr, a);
+
+        // Normal and inverse rooms side by side.
+        let id = lobby.create(d, false).unwrap();
+        assert!(!lobby.rooms[&id].ruleset.inverse);
+        let inverse: Vec<_> = lobby.room_list().iter().map(|room| room.inverse).collect();
+        assert_eq!(inverse, vec![true, false]);
+    }
+
+    #[test]
+    fn test_private_rooms() {
+        let mut lobby = lobby();
+        let (a, mut a_rx) = connect(&mut lobby, 1);
+        let (b, mut b_rx) = connect(&mut lobby, 2);
+        let (c, _c_rx) = connect(&mut lobby, 3);
+
+        let code = lobby
+            .create_private(a, Some("hunter2".to_string()), false)
+            .unwrap();
+        assert_eq!(code.len(), JOIN_CODE_LENGTH);
+        assert_eq!(next_message(&mut a_rx), rooms(&[]));
+        assert_eq!(
+            next_message(&mut a_rx),
+            ServerMessage::Code { code: code.clone() }
+        );
+        assert_eq!(joined(last_message(&mut a_rx)), Some((1, 18)));
+        assert_eq!(last_message(&mut b_rx), rooms(&[]));
+
+        let error = |result: Result<_, ProtocolError>| result.unwrap_err().code;
+        assert_eq!(lobby.join(b, 1).unwrap_err().code, ErrorCode::RoomPrivate);
+        assert_eq!(
+            error(lobby.enter(b, "ZZZZZZ", Some("hunter2"))),
+            ErrorCode::RoomNotFound
+        );
+        assert_eq!(error(lobby.enter(b, &code, None)), ErrorCode::WrongPassword);
+        assert_eq!(
+            error(lobby.enter(b, &code, Some("hunter3"))),
+            ErrorCode::WrongPassword
+        );
+        assert_eq!(lobby.enter(b, &code.to_lowercase(), Some("hunter2")), Ok(1));
+        assert_eq!(
+            error(lobby.enter(c, &code, Some("hunter2"))),
+            ErrorCode::RoomFull
+        );
+
+        let code = lobby.create_private(c, None, true).unwrap();
+        assert_eq!(lobby.room_with_code(&code), Some(2));
+        assert!(lobby.rooms[&2].ruleset.inverse);
+        assert!(lobby.room_list().is_empty());
+    }
+
+    #[test]
+    fn test_queue_features() {
+        let mut lobby = lobby();
+        let (a, _a_rx) = connect_with(&mut lobby, 1, &[]);
+        let (b, _b_rx) = connect(&mut lobby, 2);
+        let (c, _c_rx) = connect(&mut lobby, 3);
+        let (d, _d_rx) = connect_with(&mut lobby, 4, &[Feature::DualTypes]);
+        let request = |addr, generation: Option<u8>| Request {
+            addr,
+            generation: generation.and_then(Generation::from_number),
+            inverse: false,
+        };
+
+        assert_eq!(
+            lobby.enqueue(request(a, Some(1))).unwrap_err().code,
+            ErrorCode::UnsupportedFeature
+        );
+        lobby.enqueue(request(a, None)).unwrap();
+        lobby.enqueue(request(b, Some(1))).unwrap();
+        assert_eq!(lobby.queue.len(), 2);
+        lobby.enqueue(request(c, Some(1))).unwrap();
+        assert_eq!(lobby.room_of(b), lobby.room_of(c));
+        lobby.enqueue(request(d, None)).unwrap();
+        assert_eq!(lobby.room_of(a), lobby.room_of(d));
+        assert!(lobby.queue.is_empty());
+    }
+
+    #[test]
+    fn test_resume() {
+        let mut lobby = lobby();
+        let (a, a_rx) = connect(&mut lobby, 1);
+        let (b, mut b_rx) = connect(&mut lobby, 2);
+        lobby.create(a, false).unwrap();
+        lobby.join(b, 1).unwrap();
+        let room = lobby.room_mut(a).unwrap();
+        room.set_ready(a).unwrap();
+        room.set_ready(b).unwrap();
+        room.send_choices(Duration::from_secs(20));
+        let session = room.p1.as_ref().unwrap().session.clone();
+
+        drop(a_rx);
+        let (_, at) = lobby.disconnect(a, Duration::from_secs(30)).unwrap();
+        let choice = lobby.rooms[&1]
+            .p2
+            .as_ref()
+            .unwrap()
+            .choices
+            .clone()
+            .unwrap();
+        lobby
+            .room_mut(b)
+            .unwrap()
+            .set_selected(b, choice[0].clone())
+            .unwrap();
+        assert_eq!(lobby.room_of(a), Some(1));
+
+        let (c, mut c_rx) = connect(&mut lobby, 3);
+        assert_eq!(
+            lobby.resume(c, "unknown").unwrap_err().code,
+            ErrorCode::SessionNotFound
+        );
+        assert_eq!(lobby.resume(c, &session), Ok(1));
+        match last_message(&mut c_rx) {
+            ServerMessage::Snapshot {
+                room,
+                ready,
+                yours,
+                theirs,
+                selected,
+                ..
+            } => {
+                assert_eq!(room, 1);
+                assert!(ready);
+                assert_eq!(yours.map(|y| y.len()), Some(3));
+                assert_eq!(theirs, Some(choice));
+                assert_eq!(selected, None);
+            }
+            message => panic!("unexpected message {:?}", message),
+        }
+        assert_eq!(lobby.room_of(c), Some(1));
+        assert_eq!(lobby.room_of(a), None);
+
+        // An expiry scheduled for an earlier disconnection is ignored.
+        lobby.expire(&session, at);
+        assert_eq!(lobby.room_of(c), Some(1));
+
+        let (_, at) = lobby.disconnect(c, Duration::from_secs(30)).unwrap();
+        assert_eq!(
+            last_message(&mut b_rx),
+            ServerMessage::OpponentDisconnected { grace: 30 }
+        );
+        lobby.expire(&session, at);
+        assert_eq!(lobby.room_of(c), None);
+        assert!(lobby.rooms.is_empty());
+        assert_eq!(
+            next_message(&mut b_rx),
+            ServerMessage::Forfeit {
+                score: MatchScore::new(3)
+            }
+        );
+        assert_eq!(next_message(&mut b_rx), rooms(&[]));
+        assert!(lobby.idle.contains_key(&b));
+    }
+
+    #[test]
+    fn test_forfeit_requeues() {
+        let mut lobby = lobby();
+        let (a, _a_rx) = connect(&mut lobby, 1);
+        let (b, mut b_rx) = connect(&mut lobby, 2);
+        for addr in [a, b].iter() {
+            let generation = Generation::from_number(1);
+            lobby
+                .enqueue(Request {
+                    addr: *addr,
+                    generation,
+                    inverse: false,
+                })
+                .unwrap();
+        }
+        let room = lobby.room_mut(a).unwrap();
+        room.score.record(Ordering::Less);
+        let session = room.p1.as_ref().unwrap().session.clone();
+
+        let (_, at) = lobby.disconnect(a, Duration::from_secs(5)).unwrap();
+        assert_eq!(
+            last_message(&mut b_rx),
+            ServerMessage::OpponentDisconnected { grace: 5 }
+        );
+        lobby.expire(&session, at);
+        let mut score = MatchScore::new(3);
+        score.record(Ordering::Greater);
+        assert_eq!(next_message(&mut b_rx), ServerMessage::Forfeit { score });
+        assert_eq!(
+            next_message(&mut b_rx),
+            ServerMessage::Queued { position: 1 }
+        );
+        assert_eq!(lobby.queue[0].generation, Generation::from_number(1));
+        assert!(lobby.rooms.is_empty());
+    }
+
+    #[test]
+    fn test_forfeit_drops_bots() {
+        let mut lobby = lobby();
+        let (a, _a_rx) = connect(&mut lobby, 1);
+        let (b, mut b_rx) = connect(&mut lobby, 2);
+        let (tx, mut bot_rx) = unbounded();
+        let bot = Client::new(
+            SocketAddr::from(([0, 0, 0, 1], 0)),
+            tx,
+            Feature::ALL.to_vec(),
+        );
+        lobby
+            .enqueue(Request {
+                addr: a,
+                generation: None,
+                inverse: false,
+            })
+            .unwrap();
+        lobby.seat_bot(a, bot).unwrap();
+        let session = lobby
+            .room_mut(a)
+            .unwrap()
+            .p1
+            .as_ref()
+            .unwrap()
+            .session
+            .clone();
+
+        let (_, at) = lobby.disconnect(a, Duration::from_secs(5)).unwrap();
+        lobby.expire(&session, at);
+        assert!(matches!(
+            last_message(&mut bot_rx),
+            ServerMessage::Forfeit { .. }
+        ));
+        assert!(lobby.queue.is_empty());
+        assert_eq!(lobby.idle.len(), 1);
+
+        // The next player waits for a human opponent.
+        lobby
+            .enqueue(Request {
+                addr: b,
+                generation: None,
+                inverse: false,
+            })
+            .unwrap();
+        assert_eq!(
+            last_message(&mut b_rx),
+            ServerMessage::Queued { position: 1 }
+        );
+    }
+
+    #[test]
+    fn test_seat_bot() {
+        let mut lobby = lobby();
+        let (a, mut a_rx) = connect(&mut lobby, 1);
+        let (b, mut b_rx) = connect(&mut lobby, 2);
+        let bot = |port| {
+            let (tx, rx) = unbounded();
+            let addr = SocketAddr::from(([0, 0, 0, 1], port));
+            (Client::new(addr, tx, Feature::ALL.to_vec()), rx)
+        };
+
+        // In a new room from the lobby.
+        let (first, mut first_rx) = bot(1);
+        assert_eq!(lobby.seat_bot(a, first), Ok(1));
+        assert_eq!(joined(last_message(&mut a_rx)), Some((1, 18)));
+        assert_eq!(joined(last_message(&mut first_rx)), Some((1, 18)));
+        assert!(lobby.rooms[&1].is_full());
+
+        // Nobody else fits in a full room.
+        let (second, _) = bot(2);
+        assert_eq!(
+            lobby.seat_bot(a, second).unwrap_err().code,
+            ErrorCode::RoomFull
+        );
+        assert_eq!(lobby.idle.len(), 1);
+
+        // Against a waiting client, with the generation it asked for.
+        let generation = Generation::from_number(1);
+        lobby
+            .enqueue(Request {
+                addr: b,
+                generation,
+                inverse: false,
+            })
+            .unwrap();
+        let (third, _) = bot(3);
+        assert_eq!(lobby.seat_bot(b, third), Ok(2));
+        assert_eq!(joined(last_message(&mut b_rx)), Some((2, 15)));
+        assert!(lobby.queue.is_empty());
+        assert!(lobby.idle.is_empty());
+    }
+
+    #[test]
+    fn test_spectators() {
+        let mut lobby = lobby();
+        let (a, _a_rx) = connect(&mut lobby, 1);
+        let (b, _b_rx) = connect(&mut lobby, 2);
+        let (c, mut c_rx) = connect(&mut lobby, 3);
+        let (d, mut d_rx) = connect(&mut lobby, 4);
+        lobby.create(a, false).unwrap();
+        lobby.join(b, 1).unwrap();
+
+        assert_eq!(lobby.watch(c, 2).unwrap_err().code, ErrorCode::RoomNotFound);
+        last_message(&mut c_rx);
+        lobby.watch(c, 1).unwrap();
+        lobby.watch(d, 1).unwrap();
+        assert!(matches!(
+            next_message(&mut c_rx),
+            ServerMessage::Watching {
+                room: 1,
+                p1: None,
+                ..
+            }
+        ));
+        assert_eq!(lobby.room_list()[0].spectators, 2);
+        assert_eq!(lobby.room_of(c), None);
+        assert_eq!(lobby.watched_room(c), Some(1));
+
+        // Spectators are pinged like players.
+        let ping = ServerMessage::Ping { id: 1, rtt: None };
+        lobby.send_msg(d, &ping);
+        assert_eq!(last_message(&mut d_rx), ping);
+
+        let room = lobby.room_mut(a).unwrap();
+        room.set_ready(a).unwrap();
+        room.set_ready(b).unwrap();
+        room.send_choices(Duration::from_secs(20));
+        let p1 = room.p1.as_ref().unwrap().choices.clone().unwrap();
+        let p2 = room.p2.as_ref().unwrap().choices.clone().unwrap();
+        room.set_selected(b, p2[0].clone()).unwrap();
+        room.set_selected(a, p1[1].clone()).unwrap();
+        room.send_outcomes(Duration::from_secs(30));
+        assert_eq!(
+            next_message(&mut c_rx),
+            ServerMessage::Dealt {
+                p1: p1.clone(),
+                p2: p2.clone()
+            }
+        );
+        assert_eq!(
+            next_message(&mut c_rx),
+            ServerMessage::Picked {
+                player: 2,
+                choice: p2[0].clone()
+            }
+        );
+        assert_eq!(
+            next_message(&mut c_rx),
+            ServerMessage::Picked {
+                player: 1,
+                choice: p1[1].clone()
+            }
+        );
+        match next_message(&mut c_rx) {
+            ServerMessage::Round {
+                p1: m1,
+                p2: m2,
+                score,
+                ..
+            } => {
+                assert_eq!(m1.attacker, p1[1]);
+                assert_eq!(m2.attacker, p2[0]);
+                assert_eq!(score.round, 1);
+            }
+            message => panic!("unexpected message {:?}", message),
+        }
+
+        lobby.unwatch(c).unwrap();
+        assert_eq!(lobby.unwatch(c).unwrap_err().code, ErrorCode::NotSpectating);
+        assert!(lobby.idle.contains_key(&c));
+
+        lobby.leave(a);
+        lobby.leave(b);
+        assert!(lobby.rooms.is_empty());
+        assert_eq!(last_message(&mut d_rx), rooms(&[]));
+        assert!(lobby.idle.contains_key(&d));
+    }
+}
@@ -1,3 +1,9 @@
+pub mod bot;
+pub mod chart_file;
 pub mod client;
 pub mod clients;
+pub mod config;
+pub mod heartbeat;
+pub mod lobby;
 pub mod utils;
+pub mod validation;
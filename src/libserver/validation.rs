@@ -0,0 +1,342 @@
+use crate::libserver::chart_file::{ChartFile, ChartRow};
+use std::fmt;
+use typestrainer::{
+    chart::{effectiveness, Ruleset},
+    types::PokemonType,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum Diagnostic {
+    UnknownType {
+        location: String,
+        name: String,
+    },
+    Duplicate {
+        location: String,
+        name: String,
+    },
+    MissingRow {
+        attacker: String,
+    },
+    Contradiction {
+        attacker: String,
+        defender: String,
+    },
+    MissingFromReference {
+        name: String,
+    },
+    MissingFromChart {
+        name: String,
+    },
+    Difference {
+        attacker: String,
+        defender: String,
+        found: f32,
+        expected: f32,
+    },
+}
+
+impl Diagnostic {
+    pub fn is_error(&self) -> bool {
+        match self {
+            Diagnostic::UnknownType { .. } | Diagnostic::Contradiction { .. } => true,
+            // A type listed twice would be dealt twice.
+            Diagnostic::Duplicate { location, .. } => location == "types",
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Diagnostic::UnknownType { location, name } => {
+                write!(f, "error: unknown type '{}' in {}", name, location)
+            }
+            Diagnostic::Duplicate { location, name } => {
+                let level = if self.is_error() { "error" } else { "warning" };
+                write!(f, "{}: '{}' is listed twice in {}", level, name, location)
+            }
+            Diagnostic::MissingRow { attacker } => {
+                write!(
+                    f,
+                    "warning: no row for '{}', it is neutral against everything",
+                    attacker
+                )
+            }
+            Diagnostic::Contradiction { attacker, defender } => write!(
+                f,
+                "error: '{}' is listed more than once against '{}'",
+                attacker, defender
+            ),
+            Diagnostic::MissingFromReference { name } => {
+                write!(f, "note: '{}' is not in the reference chart", name)
+            }
+            Diagnostic::MissingFromChart { name } => {
+                write!(f, "note: '{}' from the reference chart is missing", name)
+            }
+            Diagnostic::Difference {
+                attacker,
+                defender,
+                found,
+                expected,
+            } => write!(
+                f,
+                "note: {} vs {} is {}x, the reference chart says {}x",
+                attacker, defender, found, expected
+            ),
+        }
+    }
+}
+
+fn check_names(
+    names: &[String],
+    location: &str,
+    is_known: impl Fn(&str) -> bool,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for (i, name) in names.iter().enumerate() {
+        if !is_known(name) {
+            diagnostics.push(Diagnostic::UnknownType {
+                location: location.to_string(),
+                name: name.clone(),
+            });
+        }
+        if names[..i].contains(name) {
+            diagnostics.push(Diagnostic::Duplicate {
+                location: location.to_string(),
+                name: name.clone(),
+            });
+        }
+    }
+}
+
+fn check_row(attacker: &str, row: &ChartRow, types: &[String], diagnostics: &mut Vec<Diagnostic>) {
+    let lists = [
+        ("super_effective", &row.super_effective),
+        ("not_very_effective", &row.not_very_effective),
+        ("no_effect", &row.no_effect),
+    ];
+
+    for (list, names) in lists.iter() {
+        check_names(
+            names,
+            &format!("[{}] {}", attacker, list),
+            |name| types.iter().any(|t| t == name),
+            diagnostics,
+        );
+    }
+
+    for (i, (_, names)) in lists.iter().enumerate() {
+        for name in names.iter() {
+            if lists[i + 1..].iter().any(|(_, other)| other.contains(name)) {
+                diagnostics.push(Diagnostic::Contradiction {
+                    attacker: attacker.to_string(),
+                    defender: name.clone(),
+                });
+            }
+        }
+    }
+}
+
+fn compare(ruleset: &Ruleset, reference: &Ruleset, diagnostics: &mut Vec<Diagnostic>) {
+    for name in ruleset.types.iter() {
+        if !reference.types.contains(name) {
+            diagnostics.push(Diagnostic::MissingFromReference {
+                name: name.to_string(),
+            });
+        }
+    }
+    for name in reference.types.iter() {
+        if !ruleset.types.contains(name) {
+            diagnostics.push(Diagnostic::MissingFromChart {
+                name: name.to_string(),
+            });
+        }
+    }
+
+    for attacker in ruleset.types.iter() {
+        for defender in ruleset.types.iter() {
+            if !reference.types.contains(attacker) || !reference.types.contains(defender) {
+                continue;
+            }
+            let found = effectiveness(&ruleset.chart, *attacker, *defender);
+            let expected = effectiveness(&reference.chart, *attacker, *defender);
+            if (found - expected).abs() > f32::EPSILON {
+                diagnostics.push(Diagnostic::Difference {
+                    attacker: attacker.to_string(),
+                    defender: defender.to_string(),
+                    found,
+                    expected,
+                });
+            }
+        }
+    }
+}
+
+pub fn validate(file: &ChartFile, reference: Option<&Ruleset>) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+
+    check_names(
+        &file.types,
+        "types",
+        |name| name.parse::<PokemonType>().is_ok(),
+        &mut diagnostics,
+    );
+
+    for (attacker, row) in file.rows.iter() {
+        if !file.types.contains(attacker) {
+            diagnostics.push(Diagnostic::UnknownType {
+                location: "row names".to_string(),
+                name: attacker.clone(),
+            });
+        }
+        check_row(attacker, row, &file.types, &mut diagnostics);
+    }
+
+    for attacker in file.types.iter() {
+        if !file.rows.contains_key(attacker) {
+            diagnostics.push(Diagnostic::MissingRow {
+                attacker: attacker.clone(),
+            });
+        }
+    }
+
+    if let (Some(reference), Ok(ruleset)) = (reference, file.to_ruleset()) {
+        compare(&ruleset, reference, &mut diagnostics);
+    }
+
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use typestrainer::chart::Generation;
+
+    #[test]
+    fn test_builtin_charts_are_valid() {
+        for generation in &[Generation::Gen1, Generation::Gen2To5, Generation::Gen6] {
+            let ruleset = Ruleset::from_generation(*generation);
+            let file = ChartFile::from_ruleset(&ruleset);
+            assert_eq!(validate(&file, Some(&ruleset)), vec![]);
+        }
+    }
+
+    #[test]
+    fn test_bundled_chart_is_valid() {
+        let file = ChartFile::load("charts/gen6.toml").unwrap();
+        let reference = Ruleset::from_generation(Generation::Gen6);
+        assert_eq!(validate(&file, Some(&reference)), vec![]);
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let file = ChartFile::parse(
+            r#"
+            types = ["grass", "ghost", "normal", "fire", "sound", "fire"]
+
+            [grass]
+            not_very_effective = ["fire", "poison", "poison"]
+
+            [ghost]
+            super_effective = ["ghost"]
+            not_very_effective = ["normal"]
+            no_effect = ["normal"]
+
+            [water]
+            super_effective = ["fire"]
+            "#,
+        )
+        .unwrap();
+        let diagnostics = validate(&file, None);
+
+        assert!(diagnostics.contains(&Diagnostic::UnknownType {
+            location: "types".to_string(),
+            name: "sound".to_string()
+        }));
+        assert!(diagnostics.contains(&Diagnostic::Duplicate {
+            location: "types".to_string(),
+            name: "fire".to_string()
+        }));
+        assert!(diagnostics.contains(&Diagnostic::Duplicate {
+            location: "[grass] not_very_effective".to_string(),
+            name: "poison".to_string()
+        }));
+        assert!(diagnostics.contains(&Diagnostic::UnknownType {
+            location: "[grass] not_very_effective".to_string(),
+            name: "poison".to_string()
+        }));
+        assert!(diagnostics.contains(&Diagnostic::UnknownType {
+            location: "row names".to_string(),
+            name: "water".to_string()
+        }));
+        assert!(diagnostics.contains(&Diagnostic::MissingRow {
+            attacker: "normal".to_string()
+        }));
+        assert!(diagnostics.contains(&Diagnostic::Contradiction {
+            attacker: "ghost".to_string(),
+            defender: "normal".to_string()
+        }));
+        assert!(diagnostics.iter().any(|d| d.is_error()));
+    }
+
+    #[test]
+    fn test_reference_differences() {
+        let file = ChartFile::parse(
+            r#"
+            types = ["ghost", "normal", "fairy", "water", "grass", "ice"]
+
+            [ghost]
+            super_effective = ["ghost"]
+            not_very_effective = ["normal"]
+
+            [normal]
+            no_effect = ["ghost"]
+
+            [fairy]
+            "#,
+        )
+        .unwrap();
+        let reference = Ruleset::from_generation(Generation::Gen2To5);
+        let diagnostics = validate(&file, Some(&reference));
+
+        assert!(diagnostics.contains(&Diagnostic::Difference {
+            attacker: "ghost".to_string(),
+            defender: "normal".to_string(),
+            found: 0.5,
+            expected: 0.0,
+        }));
+        assert!(diagnostics.contains(&Diagnostic::MissingFromReference {
+            name: "fairy".to_string()
+        }));
+        assert!(diagnostics.contains(&Diagnostic::MissingFromChart {
+            name: "fire".to_string()
+        }));
+        assert!(!diagnostics.iter().any(|d| d.is_error()));
+    }
+
+    #[test]
+    fn test_duplicate_type() {
+        let file = ChartFile::parse(
+            r#"
+            types = ["fire", "grass", "ghost", "normal", "water", "ice", "grass"]
+
+            [grass]
+            super_effective = ["water", "water"]
+            "#,
+        )
+        .unwrap();
+        let diagnostics = validate(&file, None);
+        let errors: Vec<_> = diagnostics.iter().filter(|d| d.is_error()).collect();
+
+        assert_eq!(
+            errors,
+            vec![&Diagnostic::Duplicate {
+                location: "types".to_string(),
+                name: "grass".to_string()
+            }]
+        );
+        assert!(file.to_ruleset().is_err());
+    }
+}
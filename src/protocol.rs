@@ -0,0 +1,440 @@
+use crate::{
+    chart::Matchup,
+    score::MatchScore,
+    types::{Choice, PokemonType},
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{cmp::Ordering, fmt};
+
+// Bumped on every change to the messages below.
+pub const PROTOCOL_VERSION: u32 = 9;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Hello {
+        client: String,
+        client_version: String,
+        features: Vec<Feature>,
+    },
+    Find {
+        generation: Option<u8>,
+        inverse: bool,
+    },
+    Cancel,
+    List,
+    Create {
+        inverse: bool,
+    },
+    CreatePrivate {
+        password: Option<String>,
+        inverse: bool,
+    },
+    Join {
+        room: u32,
+    },
+    Enter {
+        code: String,
+        password: Option<String>,
+    },
+    Resume {
+        session: String,
+    },
+    Watch {
+        room: u32,
+    },
+    Unwatch,
+    // Plays against a server bot, with its default strategy unless one is
+    // named.
+    Bot {
+        strategy: Option<String>,
+    },
+    Pong {
+        id: u64,
+    },
+    Ready,
+    Selected {
+        choice: Choice,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Welcome {
+        server: String,
+        server_version: String,
+        features: Vec<Feature>,
+    },
+    Rooms {
+        rooms: Vec<RoomInfo>,
+    },
+    Queued {
+        position: usize,
+    },
+    Code {
+        code: String,
+    },
+    Joined {
+        room: u32,
+        types: Vec<PokemonType>,
+        session: String,
+    },
+    // The state of the current round, sent to a resumed client.
+    Snapshot {
+        room: u32,
+        code: Option<String>,
+        types: Vec<PokemonType>,
+        score: MatchScore,
+        ready: bool,
+        yours: Option<Vec<Choice>>,
+        theirs: Option<Vec<Choice>>,
+        selected: Option<Choice>,
+        time: Option<u64>,
+    },
+    // Times are in seconds: to select, and before the next round starts on
+    // its own.
+    Choices {
+        yours: Vec<Choice>,
+        theirs: Vec<Choice>,
+        time: u64,
+    },
+    Outcome {
+        outcome: Outcome,
+        yours: Matchup,
+        theirs: Matchup,
+        score: MatchScore,
+        next_round: u64,
+    },
+    // The round ended with a player who did not select in time.
+    Timeout {
+        outcome: Outcome,
+        score: MatchScore,
+        next_round: u64,
+    },
+    Left,
+    // The opponent lost its connection and has `grace` seconds to come back
+    // before forfeiting.
+    OpponentDisconnected {
+        grace: u64,
+    },
+    OpponentReconnected,
+    // The match is won by forfeit, the player is back in the lobby or queue.
+    Forfeit {
+        score: MatchScore,
+    },
+    // Sent to spectators, with players told apart as 1 and 2.
+    Watching {
+        room: u32,
+        types: Vec<PokemonType>,
+        score: MatchScore,
+        p1: Option<Vec<Choice>>,
+        p2: Option<Vec<Choice>>,
+        p1_pick: Option<Choice>,
+        p2_pick: Option<Choice>,
+    },
+    Dealt {
+        p1: Vec<Choice>,
+        p2: Vec<Choice>,
+    },
+    Picked {
+        player: u8,
+        choice: Choice,
+    },
+    Round {
+        outcome: Outcome,
+        p1: Matchup,
+        p2: Matchup,
+        score: MatchScore,
+    },
+    // Answered with a pong, `rtt` is the last round trip time in ms.
+    Ping {
+        id: u64,
+        rtt: Option<u64>,
+    },
+    Error(ProtocolError),
+}
+
+// Optional parts of the game, only used when both players support them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    DualTypes,
+    Rulesets,
+    // Features of newer versions, ignored.
+    #[serde(other)]
+    Unknown,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 2] = [Feature::DualTypes, Feature::Rulesets];
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RoomInfo {
+    pub id: u32,
+    pub players: usize,
+    pub spectators: usize,
+    pub inverse: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Won,
+    Lost,
+    Tie,
+}
+
+impl From<Ordering> for Outcome {
+    fn from(ordering: Ordering) -> Outcome {
+        match ordering {
+            Ordering::Greater => Outcome::Won,
+            Ordering::Less => Outcome::Lost,
+            Ordering::Equal => Outcome::Tie,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InvalidMessage,
+    UnsupportedVersion,
+    HandshakeRequired,
+    UnsupportedFeature,
+    NotInRoom,
+    AlreadyInRoom,
+    RoomNotFound,
+    RoomFull,
+    RoomPrivate,
+    WrongPassword,
+    AlreadyQueued,
+    NotQueued,
+    SessionNotFound,
+    Spectating,
+    NotSpectating,
+    AlreadyReady,
+    ChoicesNotDealt,
+    AlreadySelected,
+    InvalidChoice,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProtocolError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl ProtocolError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> ProtocolError {
+        ProtocolError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({:?})", self.message, self.code)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope<T> {
+    version: u32,
+    #[serde(flatten)]
+    message: T,
+}
+
+pub fn encode<T: Serialize>(message: &T) -> String {
+    let envelope = Envelope {
+        version: PROTOCOL_VERSION,
+        message,
+    };
+    serde_json::to_string(&envelope).expect("Cannot encode message !")
+}
+
+pub fn decode<T: DeserializeOwned>(text: &str) -> Result<T, ProtocolError> {
+    #[derive(Deserialize)]
+    struct Version {
+        version: u32,
+    }
+
+    let invalid =
+        |e: serde_json::Error| ProtocolError::new(ErrorCode::InvalidMessage, e.to_string());
+    let Version { version } = serde_json::from_str(text).map_err(invalid)?;
+    if version != PROTOCOL_VERSION {
+        return Err(ProtocolError::new(
+            ErrorCode::UnsupportedVersion,
+            format!(
+                "protocol version {} is not supported, expected {}",
+                version, PROTOCOL_VERSION
+            ),
+        ));
+    }
+    let envelope: Envelope<T> = serde_json::from_str(text).map_err(invalid)?;
+    Ok(envelope.message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chart::{make_chart, Generation};
+    use crate::types::PokemonType::*;
+    use std::cmp::Ordering;
+
+    fn round_trip<T>(message: T)
+    where
+        T: Serialize + DeserializeOwned + PartialEq + fmt::Debug,
+    {
+        assert_eq!(decode::<T>(&encode(&message)), Ok(message));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        round_trip(ClientMessage::Hello {
+            client: "client".to_string(),
+            client_version: "0.1.0".to_string(),
+            features: Feature::ALL.to_vec(),
+        });
+        round_trip(ClientMessage::Find {
+            generation: None,
+            inverse: false,
+        });
+        round_trip(ClientMessage::Create { inverse: true });
+        round_trip(ClientMessage::Cancel);
+        round_trip(ClientMessage::Pong { id: 7 });
+        round_trip(ClientMessage::Bot {
+            strategy: Some("nash".to_string()),
+        });
+        round_trip(ClientMessage::Enter {
+            code: "ABC234".to_string(),
+            password: Some("secret".to_string()),
+        });
+        round_trip(ClientMessage::Resume {
+            session: "token".to_string(),
+        });
+        round_trip(ClientMessage::Selected {
+            choice: vec![Fire, Ground],
+        });
+
+        round_trip(ServerMessage::Welcome {
+            server: "server".to_string(),
+            server_version: "0.1.0".to_string(),
+            features: vec![Feature::Rulesets],
+        });
+        round_trip(ServerMessage::Rooms {
+            rooms: vec![RoomInfo {
+                id: 1,
+                players: 2,
+                spectators: 3,
+                inverse: false,
+            }],
+        });
+        round_trip(ServerMessage::Joined {
+            room: 3,
+            types: Generation::Gen1.types(),
+            session: "token".to_string(),
+        });
+        round_trip(ServerMessage::Snapshot {
+            room: 3,
+            code: Some("ABC234".to_string()),
+            types: Generation::Gen1.types(),
+            score: MatchScore::new(3),
+            ready: true,
+            yours: Some(vec![vec![Bug], vec![Dark, Fire]]),
+            theirs: Some(vec![vec![Grass, Ice], vec![Rock]]),
+            selected: Some(vec![Bug]),
+            time: Some(12),
+        });
+        round_trip(ServerMessage::Choices {
+            yours: vec![vec![Bug], vec![Dark, Fire]],
+            theirs: vec![vec![Grass, Ice], vec![Rock]],
+            time: 20,
+        });
+        let chart = make_chart(Generation::Gen6);
+        let mut score = MatchScore::new(3);
+        score.record(Ordering::Greater);
+        round_trip(ServerMessage::Outcome {
+            outcome: Outcome::Won,
+            yours: Matchup::new(&chart, &[Fire, Ground], &[Steel, Fairy]),
+            theirs: Matchup::new(&chart, &[Steel, Fairy], &[Fire, Ground]),
+            score,
+            next_round: 30,
+        });
+        round_trip(ServerMessage::Timeout {
+            outcome: Outcome::from(Ordering::Less),
+            score,
+            next_round: 30,
+        });
+        round_trip(ServerMessage::Left);
+        round_trip(ServerMessage::Ping {
+            id: 7,
+            rtt: Some(42),
+        });
+        round_trip(ServerMessage::OpponentDisconnected { grace: 30 });
+        round_trip(ServerMessage::Forfeit {
+            score: MatchScore::new(5),
+        });
+        round_trip(ServerMessage::Picked {
+            player: 2,
+            choice: vec![Water],
+        });
+        round_trip(ServerMessage::Error(ProtocolError::new(
+            ErrorCode::RoomFull,
+            "room 2 is full",
+        )));
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(
+            encode(&ClientMessage::Selected {
+                choice: vec![Fire, Ground]
+            }),
+            r#"{"version":9,"type":"selected","choice":["fire","ground"]}"#
+        );
+        assert_eq!(
+            encode(&ServerMessage::Error(ProtocolError::new(
+                ErrorCode::NotInRoom,
+                "not in a room"
+            ))),
+            r#"{"version":9,"type":"error","code":"not_in_room","message":"not in a room"}"#
+        );
+        assert_eq!(
+            decode(r#"{"version":9,"type":"join","room":4}"#),
+            Ok(ClientMessage::Join { room: 4 })
+        );
+        assert_eq!(
+            decode(
+                r#"{"version":9,"type":"hello","client":"c","client_version":"1","features":["rulesets","chat"]}"#
+            ),
+            Ok(ClientMessage::Hello {
+                client: "c".to_string(),
+                client_version: "1".to_string(),
+                features: vec![Feature::Rulesets, Feature::Unknown],
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let code = |text: &str| decode::<ClientMessage>(text).unwrap_err().code;
+        assert_eq!(code("ready:_"), ErrorCode::InvalidMessage);
+        assert_eq!(code(r#"{"type":"ready"}"#), ErrorCode::InvalidMessage);
+        assert_eq!(
+            code(r#"{"version":2,"type":"ready"}"#),
+            ErrorCode::UnsupportedVersion
+        );
+        assert_eq!(
+            code(r#"{"version":9,"type":"dance"}"#),
+            ErrorCode::InvalidMessage
+        );
+        assert_eq!(
+            code(r#"{"version":9,"type":"selected","choice":["sound"]}"#),
+            ErrorCode::InvalidMessage
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use crate::{
+    chart::{Chart, Matchup},
+    types::{Choice, PokemonType},
+};
+use rand::{seq::SliceRandom, Rng};
+use std::{cmp::Ordering, time::Duration};
+
+pub const DUAL_TYPE_ODDS: f64 = 0.5;
+
+// Defaults of a match: its length, the time to select and the time before
+// the next round.
+pub const BEST_OF: u32 = 3;
+pub const ROUND_TIME: Duration = Duration::from_secs(20);
+pub const READY_TIME: Duration = Duration::from_secs(30);
+
+// Three choices without any type in common, each dual-typed with the given
+// odds.
+pub fn deal(rng: &mut impl Rng, types: &[PokemonType], dual_odds: f64) -> Vec<Choice> {
+    let mut pool = types.choose_multiple(rng, 6).cloned();
+    let mut choices = vec![];
+    for _ in 0..3 {
+        let size = if rng.gen_bool(dual_odds) { 2 } else { 1 };
+        choices.push(pool.by_ref().take(size).collect());
+    }
+    choices
+}
+
+// Both selections attack each other, the hardest hit wins the round for the
+// first player when greater.
+pub fn play(chart: &Chart, p1: &[PokemonType], p2: &[PokemonType]) -> (Ordering, Matchup, Matchup) {
+    let p1_attack = Matchup::new(chart, p1, p2);
+    let p2_attack = Matchup::new(chart, p2, p1);
+    let result = p1_attack
+        .multiplier()
+        .partial_cmp(&p2_attack.multiplier())
+        .unwrap();
+    (result, p1_attack, p2_attack)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chart::{compute_scores, Generation, Ruleset},
+        types::PokemonType::*,
+    };
+
+    #[test]
+    fn test_deal() {
+        let ruleset = Ruleset::from_generation(Generation::Gen6);
+        let mut rng = rand::thread_rng();
+
+        let choices = deal(&mut rng, &ruleset.types, 0.0);
+        assert_eq!(choices.len(), 3);
+        assert!(choices.iter().all(|c| c.len() == 1));
+
+        let choices = deal(&mut rng, &ruleset.types, 1.0);
+        let mut types: Vec<PokemonType> = choices.concat();
+        assert_eq!(types.len(), 6);
+        types.sort();
+        types.dedup();
+        assert_eq!(types.len(), 6);
+    }
+
+    #[test]
+    fn test_play() {
+        let ruleset = Ruleset::from_generation(Generation::Gen6);
+        let (result, p1, p2) = play(&ruleset.chart, &[Water], &[Fire, Rock]);
+        assert_eq!(result, Ordering::Greater);
+        assert_eq!(
+            (p1.multiplier(), p2.multiplier()),
+            compute_scores(&[Water], &[Fire, Rock], &ruleset.chart)
+        );
+
+        let (result, _, _) = play(&ruleset.chart, &[Normal], &[Normal]);
+        assert_eq!(result, Ordering::Equal);
+    }
+}
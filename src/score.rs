@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MatchScore {
+    pub best_of: u32,
+    pub round: u32,
+    pub wins: (u32, u32),
+}
+
+impl MatchScore {
+    pub fn new(best_of: u32) -> MatchScore {
+        MatchScore {
+            best_of,
+            round: 0,
+            wins: (0, 0),
+        }
+    }
+
+    pub fn record(&mut self, result: Ordering) {
+        self.round += 1;
+        match result {
+            Ordering::Greater => self.wins.0 += 1,
+            Ordering::Less => self.wins.1 += 1,
+            Ordering::Equal => (),
+        }
+    }
+
+    // A match is over once a player has won a majority of the rounds, or
+    // when all rounds were played and someone leads. Ties keep it going.
+    pub fn winner(&self) -> Option<Ordering> {
+        let needed = self.best_of / 2 + 1;
+        let over = self.wins.0 >= needed || self.wins.1 >= needed || self.round >= self.best_of;
+        match self.wins.0.cmp(&self.wins.1) {
+            Ordering::Equal => None,
+            leader if over => Some(leader),
+            _ => None,
+        }
+    }
+
+    pub fn swapped(&self) -> MatchScore {
+        MatchScore {
+            wins: (self.wins.1, self.wins.0),
+            ..*self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_best_of_three() {
+        let mut score = MatchScore::new(3);
+        score.record(Ordering::Greater);
+        assert_eq!(score.winner(), None);
+        score.record(Ordering::Equal);
+        assert_eq!(score.winner(), None);
+        score.record(Ordering::Greater);
+        assert_eq!(score.winner(), Some(Ordering::Greater));
+        assert_eq!(score.round, 3);
+        assert_eq!(score.swapped().winner(), Some(Ordering::Less));
+    }
+
+    #[test]
+    fn test_ties_extend_the_match() {
+        let mut score = MatchScore::new(3);
+        score.record(Ordering::Greater);
+        score.record(Ordering::Less);
+        score.record(Ordering::Equal);
+        assert_eq!(score.winner(), None);
+        score.record(Ordering::Less);
+        assert_eq!(score.winner(), Some(Ordering::Less));
+    }
+}
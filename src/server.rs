@@ -1,88 +1,407 @@
 mod libserver;
 use crate::libserver::{
-    client::Client,
-    clients::Clients,
-    utils::{compute_scores, make_strengths_graph, make_weaknesses_graph},
+    bot::{Bot, Reaction},
+    chart_file::ChartFile,
+    client::{Client, Tx},
+    config::{Bots, Command, Config, Timers},
+    heartbeat::Heartbeat,
+    lobby::{Lobby, Request},
+    utils::{HEARTBEAT_INTERVAL, HEARTBEAT_TIMEOUT},
+    validation::validate,
 };
 
 use std::{
     env,
     io::Error as IoError,
     net::SocketAddr,
+    process,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
-use futures::future::join_all;
-use futures_channel::mpsc::unbounded;
+use futures_channel::mpsc::{unbounded, UnboundedReceiver};
 use futures_util::{future, pin_mut, stream::TryStreamExt, StreamExt};
 use tokio::net::{TcpListener, TcpStream};
+use typestrainer::{
+    chart::{Generation, Ruleset},
+    protocol::{decode, encode, ClientMessage, ErrorCode, Feature, ProtocolError, ServerMessage},
+    strategy::StrategyKind,
+    types::format_choice,
+};
+
+type LobbyArc = Arc<Mutex<Lobby>>;
+
+const SERVER_NAME: &str = "typestrainer-server";
+
+fn parse_message(msg: tungstenite::Message) -> Result<ClientMessage, ProtocolError> {
+    match msg.to_text() {
+        Ok(text) => decode(text),
+        Err(e) => Err(ProtocolError::new(ErrorCode::InvalidMessage, e.to_string())),
+    }
+}
+
+fn send_error(tx: &Tx, error: ProtocolError) {
+    println!("{}", error);
+    let message = encode(&ServerMessage::Error(error));
+    tx.unbounded_send(tungstenite::Message::Text(message))
+        .unwrap();
+}
+
+// Answers the hello of a client, and returns the features both sides support.
+fn welcome(tx: &Tx, features: &[Feature]) -> Vec<Feature> {
+    let features: Vec<Feature> = Feature::ALL
+        .iter()
+        .copied()
+        .filter(|f| features.contains(f))
+        .collect();
+    let message = encode(&ServerMessage::Welcome {
+        server: SERVER_NAME.to_string(),
+        server_version: env!("CARGO_PKG_VERSION").to_string(),
+        features: features.clone(),
+    });
+    tx.unbounded_send(tungstenite::Message::Text(message))
+        .unwrap();
+    features
+}
 
-type ClientsArc = Arc<Mutex<Clients>>;
+// Ends the round `deal` of the room if it is still going at the deadline.
+fn schedule_round(lobby: LobbyArc, id: u32, deal: u64, timers: Timers) {
+    tokio::spawn(async move {
+        tokio::time::sleep(timers.round).await;
+        {
+            let mut lobby = lobby.lock().unwrap();
+            let room = match lobby.rooms.get_mut(&id) {
+                Some(room) if room.deal == deal && room.deadline.is_some() => room,
+                _ => return,
+            };
+            room.time_out(timers.on_timeout, timers.ready);
+            room.reset();
+        }
+        schedule_ready(lobby, id, deal, timers);
+    });
+}
 
-enum Action {
-    Ready,
-    Selected(String),
-    Error,
+// Starts the round after `deal` when the players are still not ready.
+fn schedule_ready(lobby: LobbyArc, id: u32, deal: u64, timers: Timers) {
+    tokio::spawn(async move {
+        tokio::time::sleep(timers.ready).await;
+        {
+            let mut lobby = lobby.lock().unwrap();
+            let room = match lobby.rooms.get_mut(&id) {
+                Some(room) if room.deal == deal => room,
+                _ => return,
+            };
+            if !room.force_ready() {
+                return;
+            }
+            println!("room {} was not ready in time, sending choices.", id);
+            room.send_choices(timers.round);
+        }
+        schedule_round(lobby, id, deal + 1, timers);
+    });
 }
 
-fn parse_action(msg: tungstenite::Message) -> Action {
-    let text = match msg.to_text() {
-        Ok(text) => text,
-        Err(_) => return Action::Error,
+// Seats a bot against the client, playing until its opponent leaves.
+fn add_bot(
+    lobby: &mut Lobby,
+    shared: &LobbyArc,
+    addr: SocketAddr,
+    strategy: StrategyKind,
+    timers: Timers,
+    bots: Bots,
+) {
+    let bot = Bot::new(strategy.build());
+    let (tx, rx) = unbounded();
+    let client = Client::new(bot.addr, tx, Feature::ALL.to_vec());
+    match lobby.seat_bot(addr, client) {
+        Ok(id) => {
+            println!("{} plays a {} bot in room {}", addr, strategy.name(), id);
+            tokio::spawn(run_bot(shared.clone(), bot, rx, timers, bots));
+        }
+        Err(e) => lobby.refuse(addr, e),
+    }
+}
+
+// Gives a bot to a client still waiting for an opponent after a while.
+fn schedule_bot(lobby: LobbyArc, addr: SocketAddr, timers: Timers, bots: Bots) {
+    let after = match bots.after {
+        Some(after) => after,
+        None => return,
     };
-    let full: Vec<&str> = text.splitn(2, ':').collect();
+    tokio::spawn(async move {
+        tokio::time::sleep(after).await;
+        let mut guard = lobby.lock().unwrap();
+        let waiting = match guard.room_of(addr) {
+            Some(id) => !guard.rooms[&id].is_full(),
+            None => guard.queue.iter().any(|queued| queued.addr == addr),
+        };
+        if waiting {
+            println!("nobody came for {}, seating a bot.", addr);
+            add_bot(&mut guard, &lobby, addr, bots.strategy, timers, bots);
+        }
+    });
+}
 
-    if full.len() < 2 {
-        return Action::Error;
+// Plays the messages a bot receives, until it leaves its room and its
+// sender is dropped.
+async fn run_bot(
+    lobby: LobbyArc,
+    mut bot: Bot,
+    mut rx: UnboundedReceiver<tungstenite::Message>,
+    timers: Timers,
+    bots: Bots,
+) {
+    while let Some(msg) = rx.next().await {
+        let message = match msg.to_text().map(decode) {
+            Ok(Ok(message)) => message,
+            _ => continue,
+        };
+        let reaction = {
+            let lobby = lobby.lock().unwrap();
+            let ruleset = lobby
+                .room_of(bot.addr)
+                .map(|id| lobby.rooms[&id].ruleset.clone());
+            bot.react(message, ruleset.as_deref())
+        };
+        let message = match reaction {
+            Reaction::Wait => continue,
+            Reaction::Send(message) => message,
+            Reaction::Think(message) => {
+                tokio::time::sleep(bots.delay).await;
+                message
+            }
+            Reaction::Leave => {
+                println!("bot {} leaves", bot.addr);
+                lobby.lock().unwrap().leave(bot.addr);
+                continue;
+            }
+        };
+        let mut guard = lobby.lock().unwrap();
+        handle_message(&mut guard, &lobby, bot.addr, message, timers, bots);
     }
+}
 
-    let (action, parameters) = (full[0], full[1]);
-    match action {
-        "ready" => Action::Ready,
-        "selected" => Action::Selected(parameters.to_string()),
-        _ => Action::Error,
+// Handles a message of a client past its hello, or of a bot.
+fn handle_message(
+    lobby: &mut Lobby,
+    shared: &LobbyArc,
+    addr: SocketAddr,
+    message: ClientMessage,
+    timers: Timers,
+    bots: Bots,
+) {
+    match message {
+        // Answered by the connection, bots are not pinged.
+        ClientMessage::Pong { .. } => (),
+        ClientMessage::Hello { .. } => {
+            let message = format!("{} already said hello", addr);
+            lobby.send_error(addr, ProtocolError::new(ErrorCode::InvalidMessage, message));
+        }
+        ClientMessage::Find {
+            generation,
+            inverse,
+        } => {
+            let request = match generation.map(Generation::from_number) {
+                Some(None) => {
+                    let message = format!("unknown generation {}", generation.unwrap());
+                    let error = ProtocolError::new(ErrorCode::InvalidMessage, message);
+                    lobby.send_error(addr, error);
+                    return;
+                }
+                generation => Request {
+                    addr,
+                    generation: generation.flatten(),
+                    inverse,
+                },
+            };
+            match lobby.enqueue(request) {
+                Ok(()) => schedule_bot(shared.clone(), addr, timers, bots),
+                Err(e) => lobby.send_error(addr, e),
+            }
+        }
+        ClientMessage::Cancel => {
+            if let Err(e) = lobby.cancel(addr) {
+                println!("{}", e);
+            }
+        }
+        ClientMessage::List => lobby.send_rooms(addr),
+        ClientMessage::Create { inverse } => match lobby.create(addr, inverse) {
+            Ok(id) => {
+                println!("{} created room {}", addr, id);
+                schedule_bot(shared.clone(), addr, timers, bots);
+            }
+            Err(e) => lobby.refuse(addr, e),
+        },
+        ClientMessage::CreatePrivate { password, inverse } => {
+            match lobby.create_private(addr, password, inverse) {
+                Ok(code) => {
+                    println!("{} created private room {}", addr, code);
+                    schedule_bot(shared.clone(), addr, timers, bots);
+                }
+                Err(e) => lobby.refuse(addr, e),
+            }
+        }
+        ClientMessage::Join { room } => match lobby.join(addr, room) {
+            Ok(()) => println!("{} joined room {}", addr, room),
+            Err(e) => lobby.refuse(addr, e),
+        },
+        ClientMessage::Enter { code, password } => {
+            match lobby.enter(addr, &code, password.as_deref()) {
+                Ok(id) => println!("{} entered room {}", addr, id),
+                Err(e) => lobby.refuse(addr, e),
+            }
+        }
+        ClientMessage::Resume { session } => match lobby.resume(addr, &session) {
+            Ok(id) => println!("{} resumed in room {}", addr, id),
+            Err(e) => lobby.refuse(addr, e),
+        },
+        ClientMessage::Watch { room } => match lobby.watch(addr, room) {
+            Ok(()) => println!("{} watches room {}", addr, room),
+            Err(e) => lobby.refuse(addr, e),
+        },
+        ClientMessage::Unwatch => {
+            if let Err(e) = lobby.unwatch(addr) {
+                lobby.send_error(addr, e);
+            }
+        }
+        ClientMessage::Ready | ClientMessage::Selected { .. } | ClientMessage::Bot { .. }
+            if lobby.watched_room(addr).is_some() =>
+        {
+            let error = ProtocolError::new(
+                ErrorCode::Spectating,
+                format!("{} is watching, not playing", addr),
+            );
+            lobby.send_error(addr, error);
+        }
+        ClientMessage::Bot { strategy } => {
+            let strategy = match strategy.as_deref().map(StrategyKind::from_name) {
+                Some(None) => {
+                    let message = format!(
+                        "unknown strategy {}, expected {}",
+                        strategy.unwrap(),
+                        StrategyKind::NAMES
+                    );
+                    let error = ProtocolError::new(ErrorCode::InvalidMessage, message);
+                    lobby.send_error(addr, error);
+                    return;
+                }
+                strategy => strategy.flatten().unwrap_or(bots.strategy),
+            };
+            add_bot(lobby, shared, addr, strategy, timers, bots);
+        }
+        ClientMessage::Ready | ClientMessage::Selected { .. } if lobby.room_of(addr).is_none() => {
+            let error =
+                ProtocolError::new(ErrorCode::NotInRoom, format!("{} is not in a room", addr));
+            lobby.send_error(addr, error);
+        }
+        ClientMessage::Ready => {
+            let c = lobby.room_mut(addr).unwrap();
+            if let Err(e) = c.set_ready(addr) {
+                lobby.send_error(addr, e);
+                return;
+            }
+            println!("{} is ready", addr);
+            if c.both_ready() {
+                println!("both ready, sending choices.");
+                c.send_choices(timers.round);
+                schedule_round(shared.clone(), c.id, c.deal, timers);
+            }
+        }
+        ClientMessage::Selected { choice } => {
+            let c = lobby.room_mut(addr).unwrap();
+            println!("{} selected {}", addr, format_choice(&choice));
+            if let Err(e) = c.set_selected(addr, choice) {
+                lobby.send_error(addr, e);
+                return;
+            }
+            if c.both_selected() {
+                println!("both selected, computing outcome.");
+                c.send_outcomes(timers.ready);
+                c.reset();
+                schedule_ready(shared.clone(), c.id, c.deal, timers);
+            }
+        }
     }
 }
 
-async fn handle_connection(clients: ClientsArc, raw_stream: TcpStream, addr: SocketAddr) {
+async fn handle_connection(
+    lobby: LobbyArc,
+    raw_stream: TcpStream,
+    addr: SocketAddr,
+    grace: Duration,
+    timers: Timers,
+    bots: Bots,
+) {
     println!("Incoming TCP connection from: {}", addr);
 
     let ws_stream = tokio_tungstenite::accept_async(raw_stream)
         .await
         .expect("Error during the websocket handshake occurred");
 
+    // Until the handshake is done, the client is not in the lobby and the
+    // sender stays here. Dropping it closes the connection.
     let (tx, rx) = unbounded();
-    clients.lock().unwrap().add(Client::new(addr, tx));
+    let mut pending = Some(tx);
+    let mut greeted = false;
+    let shared = lobby.clone();
+    let heartbeat = Mutex::new(Heartbeat::new());
 
     let (outgoing, incoming) = ws_stream.split();
 
     let handle_incoming = incoming.try_for_each(|msg| {
         println!("Received a message from {}: {}", addr, msg);
+        heartbeat.lock().unwrap().seen();
 
-        match parse_action(msg) {
-            Action::Ready => {
-                let mut c = clients.lock().unwrap();
-                c.set_ready(addr);
-                println!("{} is ready", addr);
-                if c.both_ready() {
-                    println!("both ready, sending choices.");
-                    c.send_choices();
+        if !greeted {
+            let tx = match pending.take() {
+                Some(tx) => tx,
+                None => return future::ok(()),
+            };
+            match parse_message(msg) {
+                Ok(ClientMessage::Hello {
+                    client,
+                    client_version,
+                    features,
+                }) => {
+                    let features = welcome(&tx, &features);
+                    println!(
+                        "{} is {} {}, features {:?}",
+                        addr, client, client_version, features
+                    );
+                    lobby
+                        .lock()
+                        .unwrap()
+                        .connect(Client::new(addr, tx, features));
+                    greeted = true;
                 }
-            }
-            Action::Selected(type_) => {
-                let mut c = clients.lock().unwrap();
-                c.set_selected(addr, type_.clone());
-                println!("{} selected {}", addr, type_);
-                if c.both_selected() {
-                    println!("both selected, computing outcome.");
-                    c.send_outcomes();
-                    c.reset();
+                Err(e) if e.code == ErrorCode::UnsupportedVersion => send_error(&tx, e),
+                Err(e) => {
+                    send_error(&tx, e);
+                    pending = Some(tx);
+                }
+                Ok(_) => {
+                    let message = "the first message must be a hello";
+                    send_error(
+                        &tx,
+                        ProtocolError::new(ErrorCode::HandshakeRequired, message),
+                    );
+                    pending = Some(tx);
                 }
             }
-            Action::Error => {
-                println!("dafuk?");
-                clients.lock().unwrap().send_msg(addr, "dafuk?".to_string());
+            return future::ok(());
+        }
+
+        let mut lobby = lobby.lock().unwrap();
+        let message = match parse_message(msg) {
+            Ok(message) => message,
+            Err(e) => {
+                lobby.send_error(addr, e);
+                return future::ok(());
             }
+        };
+        match message {
+            ClientMessage::Pong { id } => heartbeat.lock().unwrap().pong(id),
+            message => handle_message(&mut lobby, &shared, addr, message, timers, bots),
         }
 
         future::ok(())
@@ -90,35 +409,97 @@ async fn handle_connection(clients: ClientsArc, raw_stream: TcpStream, addr: Soc
 
     let receive_from_others = rx.map(Ok).forward(outgoing);
 
-    pin_mut!(handle_incoming, receive_from_others);
-    future::select(handle_incoming, receive_from_others).await;
+    // Pings the client, and gives up on it when it stops answering.
+    let beat = async {
+        let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+        loop {
+            interval.tick().await;
+            let ping = {
+                let mut heartbeat = heartbeat.lock().unwrap();
+                if heartbeat.is_dead(HEARTBEAT_TIMEOUT) {
+                    println!("{} timed out", addr);
+                    return;
+                }
+                if let Some(rtt) = heartbeat.rtt {
+                    println!("{} round trip: {:?}", addr, rtt);
+                }
+                heartbeat.ping()
+            };
+            lobby.lock().unwrap().send_msg(addr, &ping);
+        }
+    };
+
+    // The websocket is dropped with these futures, which closes a dead
+    // connection before its grace period starts.
+    {
+        pin_mut!(handle_incoming, receive_from_others, beat);
+        let connection = future::select(handle_incoming, receive_from_others);
+        future::select(connection, beat).await;
+    }
     println!("{} disconnected", &addr);
+    let disconnected = lobby.lock().unwrap().disconnect(addr, grace);
+    if let Some((session, at)) = disconnected {
+        tokio::time::sleep(grace).await;
+        lobby.lock().unwrap().expire(&session, at);
+    }
+}
+
+fn check_chart(file: &ChartFile, reference: &Ruleset) -> bool {
+    let diagnostics = validate(file, Some(reference));
+    for diagnostic in diagnostics.iter() {
+        println!("{}", diagnostic);
+    }
+    let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+    println!(
+        "chart checked: {} error(s), {} other diagnostic(s).",
+        errors,
+        diagnostics.len() - errors
+    );
+    errors == 0
 }
 
 #[tokio::main]
 async fn main() -> Result<(), IoError> {
-    let addr = env::args()
-        .nth(1)
-        .unwrap_or_else(|| "127.0.0.1:8080".to_string());
+    let config = Config::from_args(env::args().skip(1)).expect("Invalid arguments");
 
-    let clients = ClientsArc::new(Mutex::new(Clients::default()));
+    let reference = Ruleset::from_generation(config.generation);
 
-    let listener = (TcpListener::bind(&addr).await).expect("Failed to bind");
-    let mut handles = vec![];
+    if config.command == Command::Validate {
+        let file = match config.chart {
+            Some(ref path) => ChartFile::load(path).expect("Invalid chart file"),
+            None => ChartFile::from_ruleset(&reference),
+        };
+        if !check_chart(&file, &reference) {
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let ruleset = match config.chart {
+        Some(ref path) => {
+            let file = ChartFile::load(path).expect("Invalid chart file");
+            if !check_chart(&file, &reference) {
+                process::exit(1);
+            }
+            file.to_ruleset().expect("Invalid chart file")
+        }
+        None => reference,
+    };
+
+    let lobby = LobbyArc::new(Mutex::new(Lobby::new(Arc::new(ruleset), config.best_of)));
+
+    let listener = (TcpListener::bind(&config.addr).await).expect("Failed to bind");
 
     while let Ok((stream, addr)) = listener.accept().await {
-        handles.push(tokio::spawn(handle_connection(
-            clients.clone(),
+        tokio::spawn(handle_connection(
+            lobby.clone(),
             stream,
             addr,
-        )));
-
-        if handles.len() >= 2 {
-            break;
-        }
+            config.grace,
+            config.timers,
+            config.bots,
+        ));
     }
 
-    join_all(handles).await;
-
     Ok(())
 }
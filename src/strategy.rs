@@ -0,0 +1,383 @@
+use crate::{
+    chart::{compute_scores, Chart},
+    types::{Choice, PokemonType},
+};
+use rand::{seq::SliceRandom, Rng};
+use std::cmp::Ordering;
+
+// Both hands are known before picking, so a round is a simultaneous game:
+// the payoff of each pair of choices is 1 for a win, 0 for a tie and -1 for
+// a loss, from the point of view of `mine`.
+pub fn payoffs(chart: &Chart, mine: &[Choice], theirs: &[Choice]) -> Vec<Vec<f32>> {
+    mine.iter()
+        .map(|m| {
+            theirs
+                .iter()
+                .map(|t| {
+                    let (ours, their) = compute_scores(m, t, chart);
+                    match ours.partial_cmp(&their).unwrap() {
+                        Ordering::Greater => 1.0,
+                        Ordering::Equal => 0.0,
+                        Ordering::Less => -1.0,
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Solves `matrix * x = rhs` by gaussian elimination, `None` when singular.
+fn solve_linear(mut matrix: Vec<Vec<f32>>, mut rhs: Vec<f32>) -> Option<Vec<f32>> {
+    let n = rhs.len();
+    for col in 0..n {
+        let pivot = (col..n).max_by(|a, b| {
+            matrix[*a][col]
+                .abs()
+                .partial_cmp(&matrix[*b][col].abs())
+                .unwrap()
+        })?;
+        if matrix[pivot][col].abs() < 1e-6 {
+            return None;
+        }
+        matrix.swap(col, pivot);
+        rhs.swap(col, pivot);
+        let pivot = matrix[col].clone();
+        for (row, values) in matrix.iter_mut().enumerate() {
+            if row != col {
+                let factor = values[col] / pivot[col];
+                for (value, p) in values.iter_mut().zip(pivot.iter()).skip(col) {
+                    *value -= factor * p;
+                }
+                rhs[row] -= factor * rhs[col];
+            }
+        }
+    }
+    Some((0..n).map(|i| rhs[i] / matrix[i][i]).collect())
+}
+
+// Every subset of `0..n` with `size` elements.
+fn subsets(n: usize, size: usize) -> Vec<Vec<usize>> {
+    (0..1u32 << n)
+        .filter(|mask| mask.count_ones() as usize == size)
+        .map(|mask| (0..n).filter(|i| mask & (1 << i) != 0).collect())
+        .collect()
+}
+
+// The maximin mixed strategy of the row player of a zero-sum game, and the
+// value it guarantees. The optimum is a vertex where as many rows are played
+// as columns are equalized, hands are small enough to try them all.
+pub fn solve(payoffs: &[Vec<f32>]) -> (Vec<f32>, f32) {
+    let (rows, cols) = (payoffs.len(), payoffs[0].len());
+    let guaranteed = |x: &[f32]| -> f32 {
+        (0..cols)
+            .map(|j| (0..rows).map(|i| x[i] * payoffs[i][j]).sum::<f32>())
+            .fold(f32::INFINITY, f32::min)
+    };
+
+    let mut best: Option<(Vec<f32>, f32)> = None;
+    for size in 1..=rows.min(cols) {
+        for support in subsets(rows, size) {
+            for equalized in subsets(cols, size) {
+                // Unknowns are the weights of the support and the value.
+                let mut matrix = vec![];
+                let mut rhs = vec![];
+                for j in equalized.iter() {
+                    let mut row: Vec<f32> = support.iter().map(|i| payoffs[*i][*j]).collect();
+                    row.push(-1.0);
+                    matrix.push(row);
+                    rhs.push(0.0);
+                }
+                let mut sum = vec![1.0; size];
+                sum.push(0.0);
+                matrix.push(sum);
+                rhs.push(1.0);
+
+                let weights = match solve_linear(matrix, rhs) {
+                    Some(weights) if weights[..size].iter().all(|w| *w >= -1e-6) => weights,
+                    _ => continue,
+                };
+                let mut x = vec![0.0; rows];
+                for (i, w) in support.iter().zip(weights.iter()) {
+                    x[*i] = w.max(0.0);
+                }
+                let value = guaranteed(&x);
+                if !matches!(best, Some((_, v)) if value <= v + 1e-6) {
+                    best = Some((x, value));
+                }
+            }
+        }
+    }
+    best.expect("Cannot solve an empty game !")
+}
+
+pub trait Strategy: Send {
+    // Picks one of `mine` knowing the opponent holds `theirs`.
+    fn pick(&mut self, chart: &Chart, mine: &[Choice], theirs: &[Choice]) -> Choice;
+
+    // Called after each round with the choice the opponent picked.
+    fn observe(
+        &mut self,
+        _chart: &Chart,
+        _mine: &[Choice],
+        _theirs: &[Choice],
+        _picked: &[PokemonType],
+    ) {
+    }
+}
+
+// Any choice, uniformly.
+pub struct Random;
+
+impl Strategy for Random {
+    fn pick(&mut self, _chart: &Chart, mine: &[Choice], _theirs: &[Choice]) -> Choice {
+        mine.choose(&mut rand::thread_rng())
+            .cloned()
+            .expect("Cannot pick from no choices !")
+    }
+}
+
+// The choice with the best worst case, then the best total, against all of
+// the opponent's choices.
+pub struct Greedy;
+
+impl Strategy for Greedy {
+    fn pick(&mut self, chart: &Chart, mine: &[Choice], theirs: &[Choice]) -> Choice {
+        let payoffs = payoffs(chart, mine, theirs);
+        let rank = |row: &Vec<f32>| -> (f32, f32) {
+            (
+                row.iter().cloned().fold(f32::INFINITY, f32::min),
+                row.iter().sum(),
+            )
+        };
+        let best = (0..mine.len())
+            .max_by(|a, b| rank(&payoffs[*a]).partial_cmp(&rank(&payoffs[*b])).unwrap())
+            .expect("Cannot pick from no choices !");
+        mine[best].clone()
+    }
+}
+
+// Learns how the opponent ranks its choices: how often it picks the one with
+// the best total payoff, the second best or the worst. The most rewarding
+// answer to that prediction is picked.
+pub struct Modelling {
+    // Picks by rank, starting at one so that nothing is ruled out.
+    ranks: Vec<f32>,
+}
+
+impl Modelling {
+    pub fn new() -> Modelling {
+        Modelling { ranks: vec![] }
+    }
+
+    // Their choices from their best total payoff to their worst.
+    fn ranked(chart: &Chart, mine: &[Choice], theirs: &[Choice]) -> Vec<usize> {
+        let totals: Vec<f32> = payoffs(chart, theirs, mine)
+            .iter()
+            .map(|row| row.iter().sum())
+            .collect();
+        let mut ranked: Vec<usize> = (0..theirs.len()).collect();
+        ranked.sort_by(|a, b| totals[*b].partial_cmp(&totals[*a]).unwrap());
+        ranked
+    }
+}
+
+impl Default for Modelling {
+    fn default() -> Modelling {
+        Modelling::new()
+    }
+}
+
+impl Strategy for Modelling {
+    fn pick(&mut self, chart: &Chart, mine: &[Choice], theirs: &[Choice]) -> Choice {
+        self.ranks.resize(theirs.len().max(self.ranks.len()), 1.0);
+        let mut odds = vec![0.0; theirs.len()];
+        for (rank, j) in Modelling::ranked(chart, mine, theirs).iter().enumerate() {
+            odds[*j] = self.ranks[rank];
+        }
+
+        let payoffs = payoffs(chart, mine, theirs);
+        let expected =
+            |i: usize| -> f32 { (0..theirs.len()).map(|j| odds[j] * payoffs[i][j]).sum() };
+        let best = (0..mine.len())
+            .max_by(|a, b| expected(*a).partial_cmp(&expected(*b)).unwrap())
+            .expect("Cannot pick from no choices !");
+        mine[best].clone()
+    }
+
+    fn observe(
+        &mut self,
+        chart: &Chart,
+        mine: &[Choice],
+        theirs: &[Choice],
+        picked: &[PokemonType],
+    ) {
+        self.ranks.resize(theirs.len().max(self.ranks.len()), 1.0);
+        let ranked = Modelling::ranked(chart, mine, theirs);
+        if let Some(rank) = ranked.iter().position(|j| theirs[*j] == picked) {
+            self.ranks[rank] += 1.0;
+        }
+    }
+}
+
+// Plays the optimal mixed strategy of the round, which cannot be exploited.
+pub struct Nash;
+
+impl Strategy for Nash {
+    fn pick(&mut self, chart: &Chart, mine: &[Choice], theirs: &[Choice]) -> Choice {
+        let (weights, _) = solve(&payoffs(chart, mine, theirs));
+        let mut roll: f32 = rand::thread_rng().gen();
+        for (choice, weight) in mine.iter().zip(weights.iter()) {
+            if roll < *weight {
+                return choice.clone();
+            }
+            roll -= weight;
+        }
+        // Rounding errors, the weights add up to 1.
+        mine.last().cloned().expect("Cannot pick from no choices !")
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StrategyKind {
+    Random,
+    Greedy,
+    Modelling,
+    Nash,
+}
+
+impl StrategyKind {
+    pub const NAMES: &'static str = "random, greedy, modelling or nash";
+
+    pub fn from_name(name: &str) -> Option<StrategyKind> {
+        match name {
+            "random" => Some(StrategyKind::Random),
+            "greedy" => Some(StrategyKind::Greedy),
+            "modelling" => Some(StrategyKind::Modelling),
+            "nash" => Some(StrategyKind::Nash),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StrategyKind::Random => "random",
+            StrategyKind::Greedy => "greedy",
+            StrategyKind::Modelling => "modelling",
+            StrategyKind::Nash => "nash",
+        }
+    }
+
+    pub fn build(&self) -> Box<dyn Strategy> {
+        match self {
+            StrategyKind::Random => Box::new(Random),
+            StrategyKind::Greedy => Box::new(Greedy),
+            StrategyKind::Modelling => Box::new(Modelling::new()),
+            StrategyKind::Nash => Box::new(Nash),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chart::{Generation, Ruleset},
+        round::{deal, DUAL_TYPE_ODDS},
+        types::PokemonType::*,
+    };
+
+    #[test]
+    fn test_payoffs() {
+        let ruleset = Ruleset::from_generation(Generation::Gen6);
+        let payoffs = payoffs(
+            &ruleset.chart,
+            &[vec![Water], vec![Grass]],
+            &[vec![Fire], vec![Normal]],
+        );
+        assert_eq!(payoffs, vec![vec![1.0, 0.0], vec![-1.0, 0.0]]);
+    }
+
+    #[test]
+    fn test_solve() {
+        // Rock, paper, scissors.
+        let (x, value) = solve(&[
+            vec![0.0, -1.0, 1.0],
+            vec![1.0, 0.0, -1.0],
+            vec![-1.0, 1.0, 0.0],
+        ]);
+        assert!(x.iter().all(|w| (w - 1.0 / 3.0).abs() < 1e-4));
+        assert!(value.abs() < 1e-4);
+
+        // A dominant row is always played.
+        let (x, value) = solve(&[
+            vec![1.0, 1.0, 0.0],
+            vec![0.0, 1.0, -1.0],
+            vec![-1.0, 0.0, -1.0],
+        ]);
+        assert_eq!(x, vec![1.0, 0.0, 0.0]);
+        assert_eq!(value, 0.0);
+
+        // Matching pennies with a useless third row.
+        let (x, value) = solve(&[vec![1.0, -1.0], vec![-1.0, 1.0], vec![-1.0, -1.0]]);
+        assert!((x[0] - 0.5).abs() < 1e-4 && (x[1] - 0.5).abs() < 1e-4);
+        assert!(value.abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_greedy() {
+        let ruleset = Ruleset::from_generation(Generation::Gen6);
+        let pick = Greedy.pick(
+            &ruleset.chart,
+            &[vec![Normal], vec![Water], vec![Grass]],
+            &[vec![Fire], vec![Ground]],
+        );
+        assert_eq!(pick, vec![Water]);
+    }
+
+    #[test]
+    fn test_modelling() {
+        let ruleset = Ruleset::from_generation(Generation::Gen6);
+        let (mine, theirs) = (
+            [vec![Water], vec![Grass], vec![Normal]],
+            [vec![Fire], vec![Ground], vec![Electric]],
+        );
+        let mut modelling = Modelling::new();
+        // An opponent always picking its worst choice is expected to again.
+        let worst = Modelling::ranked(&ruleset.chart, &mine, &theirs)[2];
+        for _ in 0..10 {
+            modelling.observe(&ruleset.chart, &mine, &theirs, &theirs[worst]);
+        }
+        let pick = modelling.pick(&ruleset.chart, &mine, &theirs);
+        let payoffs = payoffs(&ruleset.chart, &mine, &theirs);
+        let best = (0..3).map(|i| payoffs[i][worst]).fold(-1.0, f32::max);
+        let i = mine.iter().position(|m| *m == pick).unwrap();
+        assert_eq!(payoffs[i][worst], best);
+    }
+
+    #[test]
+    fn test_nash_against_random() {
+        let ruleset = Ruleset::from_generation(Generation::Gen6);
+        let mut rng = rand::thread_rng();
+        let (mut nash, mut random) = (StrategyKind::Nash.build(), StrategyKind::Random.build());
+        let mut total = 0.0;
+        for _ in 0..500 {
+            let mine = deal(&mut rng, &ruleset.types, DUAL_TYPE_ODDS);
+            let theirs = deal(&mut rng, &ruleset.types, DUAL_TYPE_ODDS);
+            let (m, t) = (
+                nash.pick(&ruleset.chart, &mine, &theirs),
+                random.pick(&ruleset.chart, &theirs, &mine),
+            );
+            total += payoffs(&ruleset.chart, &[m], &[t])[0][0];
+        }
+        assert!(total > 0.0);
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(StrategyKind::from_name("nash"), Some(StrategyKind::Nash));
+        assert_eq!(StrategyKind::from_name("psychic"), None);
+        for kind in [StrategyKind::Random, StrategyKind::Modelling] {
+            assert_eq!(StrategyKind::from_name(kind.name()), Some(kind));
+        }
+    }
+}
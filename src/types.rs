@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PokemonType {
+    Bug,
+    Dark,
+    Dragon,
+    Electric,
+    Fairy,
+    Fighting,
+    Fire,
+    Flying,
+    Ghost,
+    Grass,
+    Ground,
+    Ice,
+    Normal,
+    Poison,
+    Psychic,
+    Rock,
+    Steel,
+    Water,
+}
+
+impl PokemonType {
+    pub const COUNT: usize = 18;
+
+    pub const ALL: [PokemonType; PokemonType::COUNT] = [
+        PokemonType::Bug,
+        PokemonType::Dark,
+        PokemonType::Dragon,
+        PokemonType::Electric,
+        PokemonType::Fairy,
+        PokemonType::Fighting,
+        PokemonType::Fire,
+        PokemonType::Flying,
+        PokemonType::Ghost,
+        PokemonType::Grass,
+        PokemonType::Ground,
+        PokemonType::Ice,
+        PokemonType::Normal,
+        PokemonType::Poison,
+        PokemonType::Psychic,
+        PokemonType::Rock,
+        PokemonType::Steel,
+        PokemonType::Water,
+    ];
+
+    pub fn iter() -> impl Iterator<Item = PokemonType> {
+        PokemonType::ALL.iter().cloned()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PokemonType::Bug => "bug",
+            PokemonType::Dark => "dark",
+            PokemonType::Dragon => "dragon",
+            PokemonType::Electric => "electric",
+            PokemonType::Fairy => "fairy",
+            PokemonType::Fighting => "fighting",
+            PokemonType::Fire => "fire",
+            PokemonType::Flying => "flying",
+            PokemonType::Ghost => "ghost",
+            PokemonType::Grass => "grass",
+            PokemonType::Ground => "ground",
+            PokemonType::Ice => "ice",
+            PokemonType::Normal => "normal",
+            PokemonType::Poison => "poison",
+            PokemonType::Psychic => "psychic",
+            PokemonType::Rock => "rock",
+            PokemonType::Steel => "steel",
+            PokemonType::Water => "water",
+        }
+    }
+}
+
+impl fmt::Display for PokemonType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for PokemonType {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<PokemonType, String> {
+        PokemonType::iter()
+            .find(|t| t.name() == name)
+            .ok_or(format!("unknown type '{}'", name))
+    }
+}
+
+pub type Choice = Vec<PokemonType>;
+
+pub fn format_choice(choice: &[PokemonType]) -> String {
+    choice
+        .iter()
+        .map(|t| t.name())
+        .collect::<Vec<&str>>()
+        .join("/")
+}
+
+pub fn parse_choice(choice: &str) -> Result<Choice, String> {
+    let choice: Choice = choice
+        .split('/')
+        .map(str::parse)
+        .collect::<Result<_, _>>()?;
+    if choice.len() > 2 || (choice.len() == 2 && choice[0] == choice[1]) {
+        return Err(format!("invalid choice '{}'", format_choice(&choice)));
+    }
+    Ok(choice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_type() {
+        for type_ in PokemonType::iter() {
+            assert_eq!(type_.to_string().parse(), Ok(type_));
+        }
+        assert_eq!("fire".parse(), Ok(PokemonType::Fire));
+        assert!("Fire".parse::<PokemonType>().is_err());
+        assert!("sound".parse::<PokemonType>().is_err());
+    }
+
+    #[test]
+    fn test_serialize() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Hand {
+            types: Vec<PokemonType>,
+        }
+
+        let hand = Hand {
+            types: vec![PokemonType::Steel, PokemonType::Fairy],
+        };
+        let text = toml::to_string(&hand).unwrap();
+        assert_eq!(text.trim(), r#"types = ["steel", "fairy"]"#);
+        assert_eq!(toml::from_str::<Hand>(&text).unwrap(), hand);
+        assert!(toml::from_str::<Hand>(r#"types = ["sound"]"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_choice() {
+        assert_eq!(parse_choice("fire"), Ok(vec![PokemonType::Fire]));
+        assert_eq!(
+            parse_choice("steel/fairy"),
+            Ok(vec![PokemonType::Steel, PokemonType::Fairy])
+        );
+        assert_eq!(
+            format_choice(&parse_choice("steel/fairy").unwrap()),
+            "steel/fairy"
+        );
+        assert!(parse_choice("").is_err());
+        assert!(parse_choice("fire/fire").is_err());
+        assert!(parse_choice("fire/water/grass").is_err());
+        assert!(parse_choice("fire/sound").is_err());
+    }
+}